@@ -1,12 +1,13 @@
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fmt;
+use std::fs;
 
 const COUNTS: Counts = Counts {
     red: 12,
     green: 13,
     blue: 14,
 };
+const GAME_PREFIX: &str = "Game ";
 const GAME_DELIM: &str = ": ";
 const DRAW_DELIM: &str = "; ";
 const COLOR_DELIM: &str = ", ";
@@ -18,7 +19,7 @@ const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-02 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-02 p<n> | fmt <file>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -29,6 +30,11 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "fmt" => match env::args().nth(2).map(|f| read_games(&f)) {
+                Some(Ok(games)) => games.iter().for_each(|game| println!("{game}")),
+                Some(Err(e)) => eprintln!("An error occurred:\n{e}"),
+                None => eprintln!("{usage}"),
+            },
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -36,65 +42,181 @@ fn main() {
     }
 }
 
-fn get_legal_game_id(line: &str) -> Option<u32> {
-    let (game, draws) = line.split_once(GAME_DELIM).unwrap();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
 
-    for draw in draws.split(DRAW_DELIM) {
-        for color_draw in draw.splitn(3, COLOR_DELIM) {
-            let (count, color) = color_draw.split_once(COUNT_DELIM).unwrap();
+impl Color {
+    fn new(name: &str) -> Result<Self, &'static str> {
+        match name {
+            RED => Ok(Self::Red),
+            GREEN => Ok(Self::Green),
+            BLUE => Ok(Self::Blue),
+            _ => Err("unknown color in Draw"),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Red => write!(f, "{RED}"),
+            Self::Green => write!(f, "{GREEN}"),
+            Self::Blue => write!(f, "{BLUE}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Draw {
+    // kept in input order so that the draw can be written back out unchanged
+    cubes: Vec<(u32, Color)>,
+}
+
+impl Draw {
+    fn new(draw: &str) -> Result<Self, &'static str> {
+        let mut cubes: Vec<(u32, Color)> = vec![];
+
+        for color_draw in draw.split(COLOR_DELIM) {
+            let (count, color) = match color_draw.split_once(COUNT_DELIM) {
+                Some(split) => split,
+                None => return Err("no count found in Draw"),
+            };
 
-            let count: u32 = count.parse().expect("should work");
-            let limit = match color {
-                RED => COUNTS.red,
-                BLUE => COUNTS.blue,
-                GREEN => COUNTS.green,
-                _ => unreachable!("there are no other colors"),
+            let count = match count.parse() {
+                Ok(0) => return Err("zero count in Draw"),
+                Ok(count) => count,
+                Err(_) => return Err("couldn't parse count in Draw"),
             };
+            let color = Color::new(color)?;
 
-            if count > limit {
-                return None;
+            if cubes.iter().any(|(_, c)| *c == color) {
+                return Err("duplicate color in Draw");
             }
+            cubes.push((count, color));
         }
+
+        Ok(Self { cubes })
     }
 
-    let (_, id) = game.split_once(COUNT_DELIM).unwrap();
-    Some(id.parse().expect("should work"))
+    fn count(&self, color: Color) -> u32 {
+        self.cubes
+            .iter()
+            .find(|(_, c)| *c == color)
+            .map_or(0, |(count, _)| *count)
+    }
 }
 
-fn part1(filename: &str) -> u32 {
-    let file = File::open(filename).expect("Should be able to read the value");
-    let file = BufReader::new(file);
+impl fmt::Display for Draw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (count, color)) in self.cubes.iter().enumerate() {
+            if index > 0 {
+                write!(f, "{COLOR_DELIM}")?;
+            }
+            write!(f, "{count}{COUNT_DELIM}{color}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Game {
+    id: u32,
+    draws: Vec<Draw>,
+}
+
+impl Game {
+    fn new(line: &str) -> Result<Self, &'static str> {
+        let (game, draws) = match line.split_once(GAME_DELIM) {
+            Some(split) => split,
+            None => return Err("no `: ` found in Game"),
+        };
+
+        let id = match game.strip_prefix(GAME_PREFIX) {
+            Some(id) => match id.parse() {
+                Ok(id) => id,
+                Err(_) => return Err("couldn't parse id in Game"),
+            },
+            None => return Err("no leading `Game ` found in Game"),
+        };
+
+        let draws = draws
+            .split(DRAW_DELIM)
+            .map(Draw::new)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { id, draws })
+    }
 
-    file.lines().fold(0, |sum, line| {
-        let l = line.unwrap();
+    fn is_legal(&self, limit: &Counts) -> bool {
+        self.draws.iter().all(|draw| {
+            draw.count(Color::Red) <= limit.red
+                && draw.count(Color::Green) <= limit.green
+                && draw.count(Color::Blue) <= limit.blue
+        })
+    }
 
-        if let Some(id) = get_legal_game_id(&l) {
-            sum + id
-        } else {
-            sum
+    fn required(&self) -> Counts {
+        let mut counter = Counts::new();
+        self.draws.iter().for_each(|draw| counter.update_max(draw));
+        counter
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{GAME_PREFIX}{}{GAME_DELIM}", self.id)?;
+        for (index, draw) in self.draws.iter().enumerate() {
+            if index > 0 {
+                write!(f, "{DRAW_DELIM}")?;
+            }
+            write!(f, "{draw}")?;
         }
-    })
+        Ok(())
+    }
+}
+
+fn parse_games(lines: &[&str]) -> Result<Vec<Game>, &'static str> {
+    let games = lines
+        .iter()
+        .map(|line| Game::new(line))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (index, game) in games.iter().enumerate() {
+        if game.id as usize != index + 1 {
+            return Err("Game ids are not sequential");
+        }
+    }
+
+    Ok(games)
 }
 
-fn get_required_powers(line: &str) -> u32 {
-    let (_, draws) = line.split_once(GAME_DELIM).unwrap();
-    let mut counter = Counts::new();
+fn read_games(filename: &str) -> Result<Vec<Game>, &'static str> {
+    let input = match fs::read_to_string(filename) {
+        Ok(input) => input,
+        Err(_) => return Err("could not read file"),
+    };
 
-    draws
-        .split(DRAW_DELIM)
-        .for_each(|draw| counter.update_max(draw));
+    parse_games(&input.lines().collect::<Vec<_>>())
+}
+
+fn part1(filename: &str) -> u32 {
+    let games = read_games(filename).expect("Should be able to parse the games");
 
-    counter.power()
+    games
+        .iter()
+        .filter(|game| game.is_legal(&COUNTS))
+        .map(|game| game.id)
+        .sum()
 }
 
 fn part2(filename: &str) -> u32 {
-    let file = File::open(filename).expect("Should be able to read the value");
-    let file = BufReader::new(file);
+    let games = read_games(filename).expect("Should be able to parse the games");
 
-    file.lines().fold(0, |sum, line| {
-        let l = line.unwrap();
-        sum + get_required_powers(&l)
-    })
+    games.iter().map(|game| game.required().power()).sum()
 }
 
 #[test]
@@ -117,6 +239,32 @@ fn part2_puzzle() {
     assert_eq!(78111, part2(PART2_FILE));
 }
 
+#[test]
+fn game_round_trip() {
+    let input = fs::read_to_string(PART1_FILE).unwrap();
+    for line in input.lines() {
+        assert_eq!(line, Game::new(line).unwrap().to_string());
+    }
+}
+
+#[test]
+fn game_invalid() {
+    assert_eq!(
+        Err("duplicate color in Draw"),
+        Game::new("Game 1: 3 blue, 4 blue")
+    );
+    assert_eq!(Err("zero count in Draw"), Game::new("Game 1: 0 red"));
+    assert_eq!(Err("unknown color in Draw"), Game::new("Game 1: 2 pink"));
+    assert_eq!(
+        Err("no leading `Game ` found in Game"),
+        Game::new("Round 1: 2 red")
+    );
+    assert_eq!(
+        Err("Game ids are not sequential"),
+        parse_games(&["Game 1: 1 red", "Game 3: 2 red"])
+    );
+}
+
 struct Counts {
     red: u32,
     green: u32,
@@ -136,17 +284,9 @@ impl Counts {
         self.red * self.green * self.blue
     }
 
-    fn update_max(&mut self, draw: &str) {
-        for color_draw in draw.splitn(3, COLOR_DELIM) {
-            let (count, color) = color_draw.split_once(COUNT_DELIM).unwrap();
-
-            let count: u32 = count.parse().expect("should work");
-            match color {
-                RED => self.red = self.red.max(count),
-                BLUE => self.blue = self.blue.max(count),
-                GREEN => self.green = self.green.max(count),
-                _ => unreachable!("there are no other colors"),
-            };
-        }
+    fn update_max(&mut self, draw: &Draw) {
+        self.red = self.red.max(draw.count(Color::Red));
+        self.green = self.green.max(draw.count(Color::Green));
+        self.blue = self.blue.max(draw.count(Color::Blue));
     }
 }
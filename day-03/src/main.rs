@@ -1,12 +1,13 @@
+use std::collections::{BTreeSet, HashMap};
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-03 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-03 p<n> | unattached <file> \
+                 | gears <file> <symbols> <n|n+|n-m> <product|sum|max>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -17,6 +18,30 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "unattached" => match env::args().nth(2).map(|f| Schematic::new(&f)) {
+                Some(Ok(schematic)) => {
+                    for num in schematic.unattached_numbers() {
+                        println!("{} at ({}, {})", num.val, num.row, num.start);
+                    }
+                }
+                Some(Err(e)) => eprintln!("An error occurred:\n{e}"),
+                None => eprintln!("{usage}"),
+            },
+            "gears" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                match args.as_slice() {
+                    [file, symbols, count, aggregation] => {
+                        let score = GearRule::new(symbols, count, aggregation).and_then(|rule| {
+                            Schematic::new(file).map(|schematic| schematic.score(&rule))
+                        });
+                        match score {
+                            Ok(score) => println!("Score is {score}"),
+                            Err(e) => eprintln!("An error occurred:\n{e}"),
+                        }
+                    }
+                    _ => eprintln!("{usage}"),
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -24,208 +49,269 @@ fn main() {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct Number {
     val: u32,
+    row: usize,
+    // columns `start..end` hold the digits
     start: usize,
     end: usize,
 }
 
 impl Number {
-    fn parse(line: &str) -> Vec<Self> {
+    fn parse(row: usize, line: &str) -> Result<Vec<Self>, &'static str> {
         let mut nums = vec![];
-        let mut iter = line.chars().enumerate();
+        let mut iter = line.char_indices().peekable();
 
-        while let Some((mut index, mut letter)) = iter.next() {
+        while let Some((start, letter)) = iter.next() {
             if !letter.is_ascii_digit() {
                 continue;
             }
 
-            let start = index;
-            let mut digits = vec![];
-            while letter.is_ascii_digit() {
-                digits.push(letter);
-
-                match iter.next() {
-                    Some((x, y)) => {
-                        index = x;
-                        letter = y;
-                    }
-                    None => break,
-                }
+            let mut end = start + 1;
+            while let Some((index, _)) = iter.next_if(|(_, l)| l.is_ascii_digit()) {
+                end = index + 1;
             }
 
-            nums.push(Number {
-                val: digits.iter().collect::<String>().parse::<u32>().unwrap(),
+            let val = match line[start..end].parse() {
+                Ok(val) => val,
+                Err(_) => return Err("couldn't parse Number"),
+            };
+            nums.push(Self {
+                val,
+                row,
                 start,
-                end: index,
+                end,
             });
         }
 
-        nums
+        Ok(nums)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.start..self.end).map(|col| (self.row, col))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct Symbol {
     sym: char,
-    index: usize,
-    numbers: Vec<u32>,
+    row: usize,
+    col: usize,
 }
 
 impl Symbol {
-    fn parse(line: &str) -> Vec<Self> {
-        let mut syms = vec![];
-
-        for (index, sym) in line.chars().enumerate() {
-            match sym {
-                '.' | '0'..='9' => { /* not symbols */ }
-                _ => syms.push(Self {
-                    sym,
-                    index,
-                    numbers: vec![],
-                }),
-            }
-        }
-
-        syms
-    }
-
-    fn sum(&self) -> u32 {
-        self.numbers.iter().sum()
-    }
-
-    fn product(&self) -> u32 {
-        self.numbers.iter().product()
-    }
-
-    fn is_adjacent_to(&self, num: &Number) -> bool {
-        if self.index >= num.start && self.index <= num.end {
-            return true;
-        }
-
-        if num.start > 0 && self.index == num.start - 1 {
-            return true;
-        }
-
-        false
+    fn parse(row: usize, line: &str) -> Vec<Self> {
+        line.char_indices()
+            .filter_map(|(col, sym)| match sym {
+                '.' | '0'..='9' => None,
+                _ => Some(Self { sym, row, col }),
+            })
+            .collect()
     }
+}
 
-    fn add_num(&mut self, num: &Number) {
-        self.numbers.push(num.val);
-    }
+fn neighbours((row, col): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+    (row.saturating_sub(1)..=row + 1)
+        .flat_map(move |r| (col.saturating_sub(1)..=col + 1).map(move |c| (r, c)))
+        .filter(move |&pos| pos != (row, col))
 }
 
-struct SymbolData {
-    prev_sym: Vec<Symbol>,
-    prev_num: Vec<Number>,
-    curr_sym: Vec<Symbol>,
-    curr_num: Vec<Number>,
+struct Schematic {
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    // (row, col) of every digit and symbol to its index in the lists above
+    number_cells: HashMap<(usize, usize), usize>,
+    symbol_cells: HashMap<(usize, usize), usize>,
 }
 
-impl SymbolData {
-    fn new() -> Self {
-        Self {
-            prev_sym: vec![],
-            prev_num: vec![],
-            curr_sym: vec![],
-            curr_num: vec![],
+impl Schematic {
+    fn new(filename: &str) -> Result<Self, &'static str> {
+        match fs::read_to_string(filename) {
+            Ok(input) => Self::parse(&input.lines().collect::<Vec<_>>()),
+            Err(_) => Err("could not read file"),
         }
     }
 
-    fn parse(&mut self, line: String) {
-        // parse numbers from current line
-        self.curr_num = Number::parse(&line);
+    fn parse(lines: &[&str]) -> Result<Self, &'static str> {
+        let mut numbers = vec![];
+        let mut symbols = vec![];
 
-        // add current nums to previous symbols
-        for sym in self.prev_sym.iter_mut() {
-            for num in &self.curr_num {
-                if sym.is_adjacent_to(num) {
-                    sym.add_num(num);
-                } else if num.start > sym.index {
-                    break;
-                }
-            }
+        for (row, line) in lines.iter().enumerate() {
+            numbers.extend(Number::parse(row, line)?);
+            symbols.extend(Symbol::parse(row, line));
         }
 
-        // parse symbols from current line
-        self.curr_sym = Symbol::parse(&line);
+        let number_cells = numbers
+            .iter()
+            .enumerate()
+            .flat_map(|(index, num)| num.cells().map(move |pos| (pos, index)))
+            .collect();
+        let symbol_cells = symbols
+            .iter()
+            .enumerate()
+            .map(|(index, sym)| ((sym.row, sym.col), index))
+            .collect();
+
+        Ok(Self {
+            numbers,
+            symbols,
+            number_cells,
+            symbol_cells,
+        })
+    }
 
-        // add previous and current nums to current symbols
-        for sym in self.curr_sym.iter_mut() {
-            for num in &self.prev_num {
-                if sym.is_adjacent_to(num) {
-                    sym.add_num(num);
-                } else if num.start > sym.index {
-                    break;
-                }
-            }
+    fn numbers_touching(&self, sym: &Symbol) -> Vec<&Number> {
+        neighbours((sym.row, sym.col))
+            .filter_map(|pos| self.number_cells.get(&pos))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|&index| &self.numbers[index])
+            .collect()
+    }
 
-            for num in &self.curr_num {
-                if sym.is_adjacent_to(num) {
-                    sym.add_num(num);
-                } else if num.start > sym.index {
-                    break;
-                }
-            }
-        }
+    fn symbols_touching(&self, num: &Number) -> Vec<&Symbol> {
+        num.cells()
+            .flat_map(neighbours)
+            .filter_map(|pos| self.symbol_cells.get(&pos))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|&index| &self.symbols[index])
+            .collect()
     }
 
-    fn shift_left(&mut self) {
-        self.prev_sym = self.curr_sym.clone();
-        self.prev_num = self.curr_num.clone();
-        self.curr_sym = vec![];
-        self.curr_num = vec![];
+    fn unattached_numbers(&self) -> Vec<&Number> {
+        self.numbers
+            .iter()
+            .filter(|num| self.symbols_touching(num).is_empty())
+            .collect()
     }
 
     fn sum_all(&self) -> u32 {
-        self.prev_sym.iter().map(|s| s.sum()).sum()
+        self.numbers
+            .iter()
+            .filter(|num| !self.symbols_touching(num).is_empty())
+            .map(|num| num.val)
+            .sum()
     }
 
-    fn sum_gears(&self) -> u32 {
-        self.prev_sym
+    fn score(&self, rule: &GearRule) -> u64 {
+        self.symbols
             .iter()
-            .filter_map(|s| {
-                if s.sym == '*' && s.numbers.len() == 2 {
-                    Some(s.product())
-                } else {
-                    None
-                }
+            .filter(|sym| rule.symbols.contains(&sym.sym))
+            .map(|sym| self.numbers_touching(sym))
+            .filter(|nums| rule.count.allows(nums.len()))
+            .map(|nums| {
+                rule.aggregation
+                    .apply(nums.iter().map(|num| num.val as u64))
             })
             .sum()
     }
 }
 
-fn part1(filename: &str) -> u32 {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NeighbourCount {
+    Exact(usize),
+    AtLeast(usize),
+    Between(usize, usize),
+}
 
-    let mut sum = 0;
-    let mut symbol_data = SymbolData::new();
+impl NeighbourCount {
+    fn new(spec: &str) -> Result<Self, &'static str> {
+        let parse = |num: &str| match num.parse() {
+            Ok(num) => Ok(num),
+            Err(_) => Err("couldn't parse neighbour count"),
+        };
+
+        if let Some(min) = spec.strip_suffix('+') {
+            Ok(Self::AtLeast(parse(min)?))
+        } else if let Some((min, max)) = spec.split_once('-') {
+            let (min, max) = (parse(min)?, parse(max)?);
+            if min > max {
+                return Err("neighbour count range is empty");
+            }
+            Ok(Self::Between(min, max))
+        } else {
+            Ok(Self::Exact(parse(spec)?))
+        }
+    }
 
-    for line in file.lines() {
-        symbol_data.parse(line.unwrap());
-        sum += symbol_data.sum_all();
-        symbol_data.shift_left();
+    fn allows(&self, count: usize) -> bool {
+        match *self {
+            Self::Exact(n) => count == n,
+            Self::AtLeast(min) => count >= min,
+            Self::Between(min, max) => (min..=max).contains(&count),
+        }
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Aggregation {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Aggregation {
+    fn new(name: &str) -> Result<Self, &'static str> {
+        match name {
+            "product" => Ok(Self::Product),
+            "sum" => Ok(Self::Sum),
+            "max" => Ok(Self::Max),
+            _ => Err("unknown aggregation"),
+        }
+    }
+
+    fn apply(&self, vals: impl Iterator<Item = u64>) -> u64 {
+        match self {
+            Self::Product => vals.product(),
+            Self::Sum => vals.sum(),
+            Self::Max => vals.max().unwrap_or(0),
+        }
+    }
+}
 
-    sum + symbol_data.sum_all()
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct GearRule {
+    symbols: Vec<char>,
+    count: NeighbourCount,
+    aggregation: Aggregation,
 }
 
-fn part2(filename: &str) -> u32 {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+impl GearRule {
+    fn new(symbols: &str, count: &str, aggregation: &str) -> Result<Self, &'static str> {
+        if symbols.is_empty() {
+            return Err("no symbols given for GearRule");
+        }
 
-    let mut sum = 0;
-    let mut symbol_data = SymbolData::new();
+        Ok(Self {
+            symbols: symbols.chars().collect(),
+            count: NeighbourCount::new(count)?,
+            aggregation: Aggregation::new(aggregation)?,
+        })
+    }
+}
 
-    for line in file.lines() {
-        symbol_data.parse(line.unwrap());
-        sum += symbol_data.sum_gears();
-        symbol_data.shift_left();
+impl Default for GearRule {
+    fn default() -> Self {
+        Self {
+            symbols: vec!['*'],
+            count: NeighbourCount::Exact(2),
+            aggregation: Aggregation::Product,
+        }
     }
+}
 
-    sum + symbol_data.sum_all()
+fn part1(filename: &str) -> u32 {
+    Schematic::new(filename)
+        .expect("Should be able to parse the schematic")
+        .sum_all()
+}
+
+fn part2(filename: &str) -> u64 {
+    Schematic::new(filename)
+        .expect("Should be able to parse the schematic")
+        .score(&GearRule::default())
 }
 
 #[test]
@@ -247,3 +333,45 @@ fn part2_example() {
 fn part2_puzzle() {
     assert_eq!(85010461, part2(PART2_FILE));
 }
+
+#[test]
+fn schematic_queries() {
+    let schematic = Schematic::new("test1.txt").unwrap();
+
+    let unattached = schematic.unattached_numbers();
+    assert_eq!(
+        vec![114, 58],
+        unattached.iter().map(|n| n.val).collect::<Vec<_>>()
+    );
+    assert_eq!((0, 5), (unattached[0].row, unattached[0].start));
+
+    let star = &schematic.symbols[0];
+    assert_eq!(('*', 1, 3), (star.sym, star.row, star.col));
+    let touching = schematic.numbers_touching(star);
+    assert_eq!(
+        vec![467, 35],
+        touching.iter().map(|n| n.val).collect::<Vec<_>>()
+    );
+    assert_eq!(vec![star], schematic.symbols_touching(touching[1]));
+}
+
+#[test]
+fn gear_rules() {
+    let schematic = Schematic::new("test1.txt").unwrap();
+
+    let rule = GearRule::new("*", "2", "product").unwrap();
+    assert_eq!(GearRule::default(), rule);
+    assert_eq!(467835, schematic.score(&rule));
+
+    let rule = GearRule::new("*", "1+", "sum").unwrap();
+    assert_eq!(467 + 35 + 617 + 755 + 598, schematic.score(&rule));
+
+    let rule = GearRule::new("*#$+", "1-2", "max").unwrap();
+    assert_eq!(467 + 633 + 617 + 592 + 664 + 755, schematic.score(&rule));
+
+    assert_eq!(
+        Err("neighbour count range is empty"),
+        GearRule::new("*", "3-2", "sum")
+    );
+    assert_eq!(Err("unknown aggregation"), GearRule::new("*", "2", "min"));
+}
@@ -1,13 +1,13 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-04 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-04 p<n> | trace <file> <csv|json> \
+                 | variant <file> <linear|doubling|table:<n>,..> <next|every:<k>|capped>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -18,6 +18,51 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "trace" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                match args.as_slice() {
+                    [file, format] => {
+                        let trace = TraceFormat::new(format)
+                            .and_then(|format| read_cards(file).map(|cards| (format, cards)))
+                            .map(|(format, cards)| {
+                                format.write(&cascade(&cards, &CopyRule::default()))
+                            });
+                        match trace {
+                            Ok(trace) => print!("{trace}"),
+                            Err(e) => eprintln!("An error occurred:\n{e}"),
+                        }
+                    }
+                    _ => eprintln!("{usage}"),
+                }
+            }
+            "variant" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                match args.as_slice() {
+                    [file, scoring, copies] => {
+                        let result = Rules::new(scoring, copies).and_then(|rules| {
+                            read_cards(file).map(|cards| {
+                                let score = cards
+                                    .iter()
+                                    .map(|card| card.score(&rules.scoring))
+                                    .sum::<u32>();
+                                let copies = cascade(&cards, &rules.copies)
+                                    .iter()
+                                    .map(|card| card.copies)
+                                    .sum::<u32>();
+                                (score, copies)
+                            })
+                        });
+                        match result {
+                            Ok((score, copies)) => {
+                                println!("Score is {score}");
+                                println!("Card count is {copies}");
+                            }
+                            Err(e) => eprintln!("An error occurred:\n{e}"),
+                        }
+                    }
+                    _ => eprintln!("{usage}"),
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -25,56 +70,295 @@ fn main() {
     }
 }
 
-fn get_common_nums(line: &str) -> usize {
-    let (_, numbers) = line.split_once(':').unwrap();
-    let (winning_nums, our_nums) = numbers.split_once('|').unwrap();
+#[derive(Debug)]
+struct Card {
+    id: usize,
+    winning_nums: HashSet<u32>,
+    our_nums: HashSet<u32>,
+}
+
+impl Card {
+    fn new(line: &str) -> Result<Self, &'static str> {
+        let (card, numbers) = match line.split_once(':') {
+            Some(split) => split,
+            None => return Err("no `:` found in Card"),
+        };
+        let (winning_nums, our_nums) = match numbers.split_once('|') {
+            Some(split) => split,
+            None => return Err("no `|` found in Card"),
+        };
+
+        let id = match card.strip_prefix("Card") {
+            Some(id) => match id.trim().parse() {
+                Ok(id) => id,
+                Err(_) => return Err("couldn't parse id in Card"),
+            },
+            None => return Err("no leading `Card` found in Card"),
+        };
 
-    let winning_set = winning_nums.split_whitespace().collect::<HashSet<&str>>();
-    let our_set = our_nums.split_whitespace().collect::<HashSet<&str>>();
+        let parse_nums = |nums: &str| {
+            nums.split_whitespace()
+                .map(|num| match num.parse() {
+                    Ok(num) => Ok(num),
+                    Err(_) => Err("couldn't parse number in Card"),
+                })
+                .collect::<Result<HashSet<_>, _>>()
+        };
 
-    winning_set.intersection(&our_set).count()
+        Ok(Self {
+            id,
+            winning_nums: parse_nums(winning_nums)?,
+            our_nums: parse_nums(our_nums)?,
+        })
+    }
+
+    fn common_nums(&self) -> usize {
+        self.winning_nums.intersection(&self.our_nums).count()
+    }
+
+    fn score(&self, scoring: &Scoring) -> u32 {
+        scoring.score(self.common_nums())
+    }
 }
 
-fn get_score(line: &str) -> u32 {
-    let common_num_count = get_common_nums(line) as u32;
-    if common_num_count == 0 {
-        0
-    } else {
-        1 << (common_num_count - 1)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum Scoring {
+    Linear,
+    #[default]
+    Doubling,
+    // score for 0, 1, 2, .. common numbers, the last entry repeats
+    Table(Vec<u32>),
+}
+
+impl Scoring {
+    fn new(spec: &str) -> Result<Self, &'static str> {
+        match spec.split_once(':') {
+            Some(("table", "")) => Err("empty Scoring table"),
+            Some(("table", table)) => {
+                let table = table
+                    .split(',')
+                    .map(|score| match score.parse() {
+                        Ok(score) => Ok(score),
+                        Err(_) => Err("couldn't parse score in Scoring table"),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Self::Table(table))
+            }
+            Some(_) => Err("unknown Scoring"),
+            None => match spec {
+                "linear" => Ok(Self::Linear),
+                "doubling" => Ok(Self::Doubling),
+                _ => Err("unknown Scoring"),
+            },
+        }
+    }
+
+    fn score(&self, common_num_count: usize) -> u32 {
+        match self {
+            Self::Linear => common_num_count as u32,
+            Self::Doubling if common_num_count == 0 => 0,
+            Self::Doubling => 1 << (common_num_count - 1),
+            Self::Table(table) => table[common_num_count.min(table.len() - 1)],
+        }
     }
 }
 
-fn part1(filename: &str) -> u32 {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum CopyRule {
+    // copies of the next n cards, wins past the last card are lost
+    #[default]
+    NextN,
+    // copies of every k-th card after this one, n times
+    EveryKth(usize),
+    // like `NextN`, but wins past the last card go to the last card
+    Capped,
+}
 
-    file.lines().fold(0, |sum, line| {
-        let line = line.unwrap();
+impl CopyRule {
+    fn new(spec: &str) -> Result<Self, &'static str> {
+        match spec.split_once(':') {
+            Some(("every", k)) => match k.parse() {
+                Ok(0) => Err("every k-th card needs k > 0"),
+                Ok(k) => Ok(Self::EveryKth(k)),
+                Err(_) => Err("couldn't parse k in CopyRule"),
+            },
+            Some(_) => Err("unknown CopyRule"),
+            None => match spec {
+                "next" => Ok(Self::NextN),
+                "capped" => Ok(Self::Capped),
+                _ => Err("unknown CopyRule"),
+            },
+        }
+    }
+
+    // indices of the cards won by the card at `index`, may repeat
+    fn targets(&self, index: usize, common_nums: usize, card_count: usize) -> Vec<usize> {
+        let last = card_count - 1;
+        match self {
+            Self::NextN => (index + 1..=index + common_nums)
+                .filter(|&target| target <= last)
+                .collect(),
+            Self::EveryKth(k) => (1..=common_nums)
+                .map(|n| index + n * k)
+                .filter(|&target| target <= last)
+                .collect(),
+            Self::Capped if index == last => vec![],
+            Self::Capped => (index + 1..=index + common_nums)
+                .map(|target| target.min(last))
+                .collect(),
+        }
+    }
+}
 
-        sum + get_score(&line)
-    })
+#[derive(Debug, Default)]
+struct Rules {
+    scoring: Scoring,
+    copies: CopyRule,
 }
 
-fn part2(filename: &str) -> u32 {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+impl Rules {
+    fn new(scoring: &str, copies: &str) -> Result<Self, &'static str> {
+        Ok(Self {
+            scoring: Scoring::new(scoring)?,
+            copies: CopyRule::new(copies)?,
+        })
+    }
+}
+
+fn parse_cards(lines: &[&str]) -> Result<Vec<Card>, &'static str> {
+    let mut cards = lines
+        .iter()
+        .map(|line| Card::new(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    cards.sort_unstable_by_key(|card| card.id);
+
+    for (index, card) in cards.iter().enumerate() {
+        if index > 0 && card.id == cards[index - 1].id {
+            return Err("duplicate Card id");
+        }
+        if card.id != index + 1 {
+            return Err("gap in Card ids");
+        }
+    }
+
+    Ok(cards)
+}
+
+fn read_cards(filename: &str) -> Result<Vec<Card>, &'static str> {
+    match fs::read_to_string(filename) {
+        Ok(input) => parse_cards(&input.lines().collect::<Vec<_>>()),
+        Err(_) => Err("could not read file"),
+    }
+}
 
-    let mut card_copies: HashMap<usize, u32> = HashMap::new();
+#[derive(Debug, PartialEq, Eq)]
+struct CardTrace {
+    id: usize,
+    common_nums: usize,
+    copies: u32,
+    // (id of the earlier card, copies won from it)
+    won_from: Vec<(usize, u32)>,
+}
 
-    file.lines().enumerate().fold(0, |sum, (card_num, line)| {
-        let line = line.unwrap();
+// expects the cards sorted by id without gaps, as returned by `parse_cards`
+fn cascade(cards: &[Card], rule: &CopyRule) -> Vec<CardTrace> {
+    let mut trace = cards
+        .iter()
+        .map(|card| CardTrace {
+            id: card.id,
+            common_nums: card.common_nums(),
+            copies: 1,
+            won_from: vec![],
+        })
+        .collect::<Vec<_>>();
 
-        let copies_of_this_card = *card_copies.get(&card_num).unwrap_or(&1);
+    for index in 0..trace.len() {
+        let (id, copies) = (trace[index].id, trace[index].copies);
 
-        let common_nums = get_common_nums(&line);
-        if common_nums > 0 {
-            for index in (card_num + 1)..(card_num + common_nums + 1) {
-                *card_copies.entry(index).or_insert(1) += copies_of_this_card;
+        for target in rule.targets(index, trace[index].common_nums, trace.len()) {
+            let won = &mut trace[target];
+            won.copies += copies;
+            match won.won_from.last_mut() {
+                Some((from, won_copies)) if *from == id => *won_copies += copies,
+                _ => won.won_from.push((id, copies)),
             }
         }
+    }
+
+    trace
+}
+
+enum TraceFormat {
+    Csv,
+    Json,
+}
+
+impl TraceFormat {
+    fn new(name: &str) -> Result<Self, &'static str> {
+        match name {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err("unknown trace format"),
+        }
+    }
+
+    fn write(&self, trace: &[CardTrace]) -> String {
+        match self {
+            Self::Csv => {
+                let mut out = String::from("card,common_nums,copies,won_from\n");
+                for card in trace {
+                    let won_from = card
+                        .won_from
+                        .iter()
+                        .map(|(id, copies)| format!("{id}:{copies}"))
+                        .collect::<Vec<_>>()
+                        .join(";");
+                    out += &format!(
+                        "{},{},{},{won_from}\n",
+                        card.id, card.common_nums, card.copies
+                    );
+                }
+                out
+            }
+            Self::Json => {
+                let cards = trace
+                    .iter()
+                    .map(|card| {
+                        let won_from = card
+                            .won_from
+                            .iter()
+                            .map(|(id, copies)| format!("{{\"card\":{id},\"copies\":{copies}}}"))
+                            .collect::<Vec<_>>()
+                            .join(",");
+                        format!(
+                            "{{\"card\":{},\"common_nums\":{},\"copies\":{},\"won_from\":[{won_from}]}}",
+                            card.id, card.common_nums, card.copies
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",\n  ");
+                format!("[\n  {cards}\n]\n")
+            }
+        }
+    }
+}
+
+fn part1(filename: &str) -> u32 {
+    let cards = read_cards(filename).expect("Should be able to parse the cards");
+
+    cards
+        .iter()
+        .map(|card| card.score(&Scoring::default()))
+        .sum()
+}
+
+fn part2(filename: &str) -> u32 {
+    let cards = read_cards(filename).expect("Should be able to parse the cards");
 
-        sum + copies_of_this_card
-    })
+    cascade(&cards, &CopyRule::default())
+        .iter()
+        .map(|card| card.copies)
+        .sum()
 }
 
 #[test]
@@ -96,3 +380,84 @@ fn part2_example() {
 fn part2_puzzle() {
     assert_eq!(6420979, part2(PART2_FILE));
 }
+
+#[test]
+fn card_ids() {
+    let lines = ["Card 2: 1 | 1", "Card 1: 1 2 | 1 2"];
+    let cards = parse_cards(&lines).unwrap();
+    assert_eq!(vec![1, 2], cards.iter().map(|c| c.id).collect::<Vec<_>>());
+
+    let trace = cascade(&cards, &CopyRule::default());
+    assert_eq!(vec![(1, 1)], trace[1].won_from);
+    assert_eq!(
+        "card,common_nums,copies,won_from\n1,2,1,\n2,1,2,1:1\n",
+        TraceFormat::Csv.write(&trace)
+    );
+
+    assert_eq!(
+        Err("gap in Card ids"),
+        parse_cards(&["Card 1: 1 | 1", "Card 3: 1 | 1"]).map(|_| ())
+    );
+    assert_eq!(
+        Err("duplicate Card id"),
+        parse_cards(&["Card 1: 1 | 1", "Card 1: 1 | 1"]).map(|_| ())
+    );
+}
+
+#[test]
+fn card_trace() {
+    let trace = cascade(&read_cards("test2.txt").unwrap(), &CopyRule::default());
+
+    assert_eq!(
+        CardTrace {
+            id: 4,
+            common_nums: 1,
+            copies: 8,
+            won_from: vec![(1, 1), (2, 2), (3, 4)],
+        },
+        trace[3]
+    );
+    assert!(TraceFormat::Json
+        .write(&trace)
+        .contains("{\"card\":5,\"common_nums\":0,\"copies\":14,\"won_from\":[{\"card\":1,\"copies\":1},{\"card\":3,\"copies\":4},{\"card\":4,\"copies\":8}]}"));
+}
+
+#[test]
+fn card_rules() {
+    let cards = read_cards("test1.txt").unwrap();
+    let score = |scoring: &str| {
+        let rules = Rules::new(scoring, "next").unwrap();
+        cards.iter().map(|c| c.score(&rules.scoring)).sum::<u32>()
+    };
+    let copies = |copies: &str| {
+        let rules = Rules::new("doubling", copies).unwrap();
+        cascade(&cards, &rules.copies)
+            .iter()
+            .map(|c| c.copies)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(13, score("doubling"));
+    assert_eq!(4 + 2 + 2 + 1, score("linear"));
+    assert_eq!(10 + 3 + 3 + 1, score("table:0,1,3,6,10"));
+    assert_eq!(6 + 3 + 3 + 1, score("table:0,1,3,6"));
+
+    assert_eq!(vec![1, 2, 4, 8, 14, 1], copies("next"));
+    assert_eq!(vec![1, 1, 2, 2, 4, 4], copies("every:2"));
+
+    let cards = parse_cards(&["Card 1: 1 2 3 | 1 2 3", "Card 2: 4 | 4"]).unwrap();
+    let trace = cascade(&cards, &CopyRule::new("capped").unwrap());
+    assert_eq!(
+        (4, vec![(1, 3)]),
+        (trace[1].copies, trace[1].won_from.clone())
+    );
+
+    assert_eq!(
+        Err("empty Scoring table"),
+        Rules::new("table:", "next").map(|_| ())
+    );
+    assert_eq!(
+        Err("unknown CopyRule"),
+        Rules::new("linear", "prev").map(|_| ())
+    );
+}
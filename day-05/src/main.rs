@@ -1,14 +1,19 @@
-use rayon::prelude::*;
 use std::collections::BTreeMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Lines};
+use std::ops::Range;
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
+const MAP_SUFFIX: &str = " map:";
+const MAP_DELIM: &str = "-to-";
+const SEED: &str = "seed";
+const LOCATION: &str = "location";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-05 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-05 p<n> | dump <file> [<from> <to>] \
+                 | inverse <file> <from> <to> <value|start end>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -19,6 +24,47 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "dump" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let composed = match args.as_slice() {
+                    [file] => get_map_set(file).and_then(|map_set| map_set.compose()),
+                    [file, from, to] => {
+                        get_map_set(file).and_then(|map_set| map_set.compose_between(from, to))
+                    }
+                    _ => Err(usage),
+                };
+                match composed {
+                    Ok(composed) => print!("{composed}"),
+                    Err(e) => eprintln!("An error occurred:\n{e}"),
+                }
+            }
+            "inverse" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let nums = args
+                    .iter()
+                    .skip(3)
+                    .map(|arg| arg.parse::<usize>())
+                    .collect::<Result<Vec<_>, _>>();
+                match (args.get(..3), nums.as_deref()) {
+                    (Some([file, from, to]), Ok(&[start, end])) => {
+                        let ranges = get_map_set(file)
+                            .and_then(|map_set| map_set.inverse_range(from, to, start..end));
+                        match ranges {
+                            Ok(ranges) => ranges.iter().for_each(|range| println!("{range:?}")),
+                            Err(e) => eprintln!("An error occurred:\n{e}"),
+                        }
+                    }
+                    (Some([file, from, to]), Ok(&[value])) => {
+                        let values =
+                            get_map_set(file).and_then(|map_set| map_set.inverse(from, to, value));
+                        match values {
+                            Ok(values) => println!("{values:?}"),
+                            Err(e) => eprintln!("An error occurred:\n{e}"),
+                        }
+                    }
+                    _ => eprintln!("{usage}"),
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -27,7 +73,7 @@ fn main() {
 }
 
 struct Mapping {
-    src: std::ops::Range<usize>,
+    src: Range<usize>,
     dst: usize,
 }
 
@@ -39,71 +85,351 @@ impl Mapping {
         }
     }
 
-    fn map(&self, seed: &usize) -> usize {
-        self.dst + seed - self.src.start
+    fn inverse(&self) -> Self {
+        Self::new(self.dst, self.src.start, self.src.len())
     }
+}
+
+// sorts the ranges and merges the ones that overlap or touch
+fn merge(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_unstable_by_key(|range| range.start);
 
-    fn contains(&self, seed: &usize) -> bool {
-        self.src.contains(seed)
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges.into_iter().filter(|range| !range.is_empty()) {
+        match merged.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
     }
+
+    merged
+}
+
+struct Stage {
+    src: String,
+    dst: String,
+    map: BTreeMap<usize, Mapping>,
 }
 
 struct MapSet {
-    mappings: Vec<BTreeMap<usize, Mapping>>,
+    stages: Vec<Stage>,
 }
 
 impl MapSet {
     fn new() -> Self {
-        Self {
-            mappings: Vec::new(),
-        }
+        Self { stages: Vec::new() }
     }
 
-    fn add_map(&mut self) {
-        self.mappings.push(BTreeMap::new());
+    fn add_map(&mut self, header: &str) -> Result<(), &'static str> {
+        let categories = match header.strip_suffix(MAP_SUFFIX) {
+            Some(categories) => categories,
+            None => return Err("no trailing ` map:` found in map header"),
+        };
+
+        match categories.split_once(MAP_DELIM) {
+            Some((src, dst)) if !src.is_empty() && !dst.is_empty() => {
+                self.stages.push(Stage {
+                    src: src.to_string(),
+                    dst: dst.to_string(),
+                    map: BTreeMap::new(),
+                });
+                Ok(())
+            }
+            _ => Err("no `<src>-to-<dst>` found in map header"),
+        }
     }
 
-    fn parse_line(&mut self, line: &str) {
-        let elements: Vec<usize> = line.splitn(3, ' ').map(|s| s.parse().unwrap()).collect();
-        let dest_start = elements[0];
-        let source_start = elements[1];
-        let length = elements[2];
+    fn parse_line(&mut self, line: &str) -> Result<(), &'static str> {
+        let elements = line
+            .split_whitespace()
+            .map(|s| match s.parse() {
+                Ok(num) => Ok(num),
+                Err(_) => Err("couldn't parse number in map"),
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        let (dest_start, source_start, length) = match elements.as_slice() {
+            &[dest_start, source_start, length] => (dest_start, source_start, length),
+            _ => return Err("expected 3 numbers in map line"),
+        };
 
-        let len = &self.mappings.len() - 1;
         let map = Mapping::new(source_start, dest_start, length);
 
-        self.mappings[len].insert(source_start, map);
+        match self.stages.last_mut() {
+            Some(stage) => {
+                stage.map.insert(source_start, map);
+                Ok(())
+            }
+            None => Err("map line before the first map header"),
+        }
+    }
+
+    // every category may only be mapped from and to once, so that chains are unique
+    fn validate(&self) -> Result<(), &'static str> {
+        for (index, stage) in self.stages.iter().enumerate() {
+            let others = &self.stages[index + 1..];
+            if others.iter().any(|other| other.src == stage.src) {
+                return Err("ambiguous maps: a category is mapped from twice");
+            }
+            if others.iter().any(|other| other.dst == stage.dst) {
+                return Err("ambiguous maps: a category is mapped to twice");
+            }
+        }
+
+        Ok(())
+    }
+
+    // the stages leading from category `from` to category `to`
+    fn chain(&self, from: &str, to: &str) -> Result<Vec<&Stage>, &'static str> {
+        let known = |category| {
+            self.stages
+                .iter()
+                .any(|stage| stage.src == category || stage.dst == category)
+        };
+        if !known(from) || !known(to) {
+            return Err("unknown category");
+        }
+
+        let mut chain = vec![];
+        let mut current = from;
+        while current != to {
+            let stage = match self.stages.iter().find(|stage| stage.src == current) {
+                Some(stage) => stage,
+                None => return Err("broken chain: no maps lead between the categories"),
+            };
+            if chain.len() == self.stages.len() {
+                return Err("broken chain: the maps form a cycle");
+            }
+
+            chain.push(stage);
+            current = &stage.dst;
+        }
+
+        Ok(chain)
+    }
+
+    fn compose_between(&self, from: &str, to: &str) -> Result<Piecewise, &'static str> {
+        Ok(self
+            .chain(from, to)?
+            .into_iter()
+            .map(|stage| Piecewise::new(&stage.map))
+            .fold(Piecewise::default(), |composed, stage| {
+                composed.then(&stage)
+            }))
+    }
+
+    fn compose(&self) -> Result<Piecewise, &'static str> {
+        self.compose_between(SEED, LOCATION)
+    }
+
+    // all values of the stage's source that end up in `range`
+    fn unmap_range(map: &BTreeMap<usize, Mapping>, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut unmapped = vec![];
+
+        for inverse in map.values().map(Mapping::inverse) {
+            let start = inverse.src.start.max(range.start);
+            let end = inverse.src.end.min(range.end);
+            if start < end {
+                let offset = inverse.dst as i64 - inverse.src.start as i64;
+                unmapped.push(shift(start, offset)..shift(end, offset));
+            }
+        }
+
+        // values outside of every mapping map to themselves
+        let mut start = range.start;
+        for mapping in map.values() {
+            if mapping.src.start >= range.end {
+                break;
+            }
+            if mapping.src.start > start {
+                unmapped.push(start..mapping.src.start);
+            }
+            start = start.max(mapping.src.end);
+        }
+        if start < range.end {
+            unmapped.push(start..range.end);
+        }
+
+        merge(unmapped)
+    }
+
+    fn inverse_range(
+        &self,
+        from: &str,
+        to: &str,
+        range: Range<usize>,
+    ) -> Result<Vec<Range<usize>>, &'static str> {
+        Ok(self
+            .chain(to, from)?
+            .into_iter()
+            .rev()
+            .fold(vec![range], |ranges, stage| {
+                merge(
+                    ranges
+                        .into_iter()
+                        .flat_map(|range| Self::unmap_range(&stage.map, range))
+                        .collect(),
+                )
+            }))
+    }
+
+    fn inverse(&self, from: &str, to: &str, value: usize) -> Result<Vec<usize>, &'static str> {
+        Ok(self
+            .inverse_range(from, to, value..value + 1)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
+
+fn shift(value: usize, offset: i64) -> usize {
+    value.wrapping_add_signed(offset as isize)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Piece {
+    src: Range<usize>,
+    offset: i64,
+}
+
+impl Piece {
+    fn image(&self) -> Range<usize> {
+        shift(self.src.start, self.offset)..shift(self.src.end, self.offset)
+    }
+}
+
+// sorted, non-overlapping pieces; values outside of all pieces map to themselves
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Piecewise {
+    pieces: Vec<Piece>,
+}
+
+impl Piecewise {
+    fn new(map: &BTreeMap<usize, Mapping>) -> Self {
+        Self::normalised(
+            map.values()
+                .map(|mapping| Piece {
+                    src: mapping.src.clone(),
+                    offset: mapping.dst as i64 - mapping.src.start as i64,
+                })
+                .collect(),
+        )
     }
 
-    fn map(&self, seed_mapping: Mapping) -> Vec<usize> {
-        let mut seeds: Vec<usize> = seed_mapping.src.collect();
+    // drops identity pieces and merges touching pieces with equal offsets
+    fn normalised(pieces: Vec<Piece>) -> Self {
+        let mut normalised: Vec<Piece> = vec![];
 
-        for map in &self.mappings {
-            seeds.par_iter_mut().for_each(|num| {
-                if let Some(found_mapping) = map.values().find(|mapping| mapping.contains(num)) {
-                    *num = found_mapping.map(num);
+        for piece in pieces {
+            if piece.offset == 0 || piece.src.is_empty() {
+                continue;
+            }
+
+            match normalised.last_mut() {
+                Some(last) if last.src.end == piece.src.start && last.offset == piece.offset => {
+                    last.src.end = piece.src.end
                 }
-            })
+                _ => normalised.push(piece),
+            }
         }
 
-        seeds
+        Self { pieces: normalised }
+    }
+
+    // splits `range` at the piece boundaries, the gaps get an offset of 0
+    fn split(&self, range: Range<usize>) -> Vec<Piece> {
+        if range.is_empty() {
+            return vec![];
+        }
+
+        let mut split = vec![];
+        let mut start = range.start;
+
+        let first = self
+            .pieces
+            .partition_point(|piece| piece.src.end <= range.start);
+        for piece in &self.pieces[first..] {
+            if piece.src.start >= range.end {
+                break;
+            }
+
+            if piece.src.start > start {
+                split.push(Piece {
+                    src: start..piece.src.start,
+                    offset: 0,
+                });
+                start = piece.src.start;
+            }
+
+            let end = piece.src.end.min(range.end);
+            split.push(Piece {
+                src: start..end,
+                offset: piece.offset,
+            });
+            start = end;
+        }
+
+        if start < range.end {
+            split.push(Piece {
+                src: start..range.end,
+                offset: 0,
+            });
+        }
+
+        split
+    }
+
+    fn lookup(&self, value: usize) -> usize {
+        let index = self.pieces.partition_point(|piece| piece.src.end <= value);
+        match self.pieces.get(index) {
+            Some(piece) if piece.src.contains(&value) => shift(value, piece.offset),
+            _ => value,
+        }
+    }
+
+    fn map_range(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        self.split(range).iter().map(Piece::image).collect()
+    }
+
+    // the map that applies `self` first and `next` second
+    fn then(&self, next: &Self) -> Self {
+        let pieces = self
+            .split(0..usize::MAX)
+            .into_iter()
+            .flat_map(|piece| {
+                next.split(piece.image())
+                    .into_iter()
+                    .map(move |next_piece| Piece {
+                        src: shift(next_piece.src.start, -piece.offset)
+                            ..shift(next_piece.src.end, -piece.offset),
+                        offset: piece.offset + next_piece.offset,
+                    })
+            })
+            .collect();
+
+        Self::normalised(pieces)
     }
 }
 
-fn get_seeds(lines: &mut Lines<BufReader<File>>) -> Vec<Mapping> {
+impl std::fmt::Display for Piecewise {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for piece in &self.pieces {
+            writeln!(f, "{:?} {:+}", piece.src, piece.offset)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_seeds(lines: &mut Lines<BufReader<File>>) -> Vec<usize> {
     let line = lines.next().unwrap().unwrap();
     let (_, seeds) = line.split_once(':').unwrap();
 
     seeds
         .split_whitespace()
-        .map(|s| {
-            let seed = s.parse().unwrap();
-            Mapping::new(seed, seed, 1)
-        })
+        .map(|s| s.parse().unwrap())
         .collect()
 }
 
-fn get_range_seeds(lines: &mut Lines<BufReader<File>>) -> Vec<Mapping> {
+fn get_range_seeds(lines: &mut Lines<BufReader<File>>) -> Vec<Range<usize>> {
     let line = lines.next().unwrap().unwrap();
     let (_, seeds) = line.split_once(':').unwrap();
 
@@ -114,32 +440,37 @@ fn get_range_seeds(lines: &mut Lines<BufReader<File>>) -> Vec<Mapping> {
 
     seeds
         .chunks_exact(2)
-        .map(|chunk| Mapping::new(chunk[0], chunk[0], chunk[1]))
+        .map(|chunk| chunk[0]..chunk[0] + chunk[1])
         .collect()
 }
 
-fn get_map(lines: &mut Lines<BufReader<File>>) -> MapSet {
+fn get_map(lines: impl Iterator<Item = String>) -> Result<MapSet, &'static str> {
     let mut map = MapSet::new();
 
-    while let Some(Ok(mut line)) = lines.next() {
-        // skip lines that indicate a description
-        if line.ends_with(':') || line.is_empty() {
+    for line in lines {
+        if line.is_empty() {
             continue;
         }
 
-        // build map from lines
-        map.add_map();
-        while !line.is_empty() {
-            map.parse_line(&line);
-
-            match lines.next() {
-                Some(Ok(new_line)) => line = new_line,
-                _ => break,
-            }
+        if line.ends_with(':') {
+            map.add_map(&line)?;
+        } else {
+            map.parse_line(&line)?;
         }
     }
 
-    map
+    map.validate()?;
+    Ok(map)
+}
+
+fn get_map_set(filename: &str) -> Result<MapSet, &'static str> {
+    let file = match File::open(filename) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return Err("could not open file"),
+    };
+
+    // skip the seeds
+    get_map(file.lines().skip(1).map_while(Result::ok))
 }
 
 fn part1(filename: &str) -> usize {
@@ -150,15 +481,15 @@ fn part1(filename: &str) -> usize {
 
     let seed_list = get_seeds(&mut lines);
 
-    let map = get_map(&mut lines);
-
-    let mut minima = Vec::new();
-    for seed in seed_list {
-        let mapped_seeds = map.map(seed);
-        minima.push(*mapped_seeds.par_iter().min().unwrap());
-    }
+    let map = get_map(lines.map_while(Result::ok))
+        .and_then(|map| map.compose())
+        .expect("Should be able to parse the almanac");
 
-    *minima.par_iter().min().unwrap()
+    seed_list
+        .into_iter()
+        .map(|seed| map.lookup(seed))
+        .min()
+        .unwrap()
 }
 
 fn part2(filename: &str) -> usize {
@@ -169,15 +500,16 @@ fn part2(filename: &str) -> usize {
 
     let seed_list = get_range_seeds(&mut lines);
 
-    let map = get_map(&mut lines);
+    let map = get_map(lines.map_while(Result::ok))
+        .and_then(|map| map.compose())
+        .expect("Should be able to parse the almanac");
 
-    let mut minima = Vec::new();
-    for seed in seed_list {
-        let mapped_seeds = map.map(seed);
-        minima.push(*mapped_seeds.par_iter().min().unwrap());
-    }
-
-    *minima.par_iter().min().unwrap()
+    seed_list
+        .into_iter()
+        .flat_map(|seeds| map.map_range(seeds))
+        .map(|locations| locations.start)
+        .min()
+        .unwrap()
 }
 
 #[test]
@@ -195,7 +527,122 @@ fn part2_example() {
     assert_eq!(46, part2("test2.txt"));
 }
 
-// #[test]
-// fn part2_puzzle() {
-//     assert_eq!(78775051, part2(PART2_FILE));
-// }
+#[test]
+fn part2_puzzle() {
+    assert_eq!(78775051, part2(PART2_FILE));
+}
+
+#[test]
+fn piecewise_map_range() {
+    let mut map = BTreeMap::new();
+    map.insert(10, Mapping::new(10, 100, 5));
+    map.insert(20, Mapping::new(20, 0, 5));
+    let map = Piecewise::new(&map);
+
+    assert_eq!(vec![5..10, 100..105, 15..20, 0..2], map.map_range(5..22));
+    assert_eq!(vec![102..104], map.map_range(12..14));
+    assert_eq!(vec![30..40], map.map_range(30..40));
+    assert_eq!(Vec::<Range<usize>>::new(), map.map_range(7..7));
+    assert_eq!(Vec::<Range<usize>>::new(), map.map_range(12..12));
+}
+
+#[test]
+fn piecewise_compose() {
+    let map_set = get_map_set("test1.txt").unwrap();
+    let composed = map_set.compose().unwrap();
+
+    assert!(composed
+        .pieces
+        .windows(2)
+        .all(|w| w[0].src.end <= w[1].src.start));
+
+    let stages = map_set
+        .stages
+        .iter()
+        .map(|stage| Piecewise::new(&stage.map))
+        .collect::<Vec<_>>();
+    for seed in 0..200 {
+        let expected = stages.iter().fold(seed, |value, stage| stage.lookup(value));
+        assert_eq!(expected, composed.lookup(seed));
+    }
+}
+
+#[test]
+fn inverse_matches_forward() {
+    let map_set = get_map_set("test1.txt").unwrap();
+    let forward = map_set.compose().unwrap();
+
+    for seed in 0..200 {
+        let inverse = map_set
+            .inverse(LOCATION, SEED, forward.lookup(seed))
+            .unwrap();
+        assert!(inverse.contains(&seed));
+    }
+    for value in 0..200 {
+        for seed in map_set.inverse(LOCATION, SEED, value).unwrap() {
+            assert_eq!(value, forward.lookup(seed));
+        }
+    }
+
+    let soil_to_humidity = map_set.compose_between("soil", "humidity").unwrap();
+    let below_50 = map_set.inverse_range("humidity", "soil", 0..50).unwrap();
+    for soil in 0..200 {
+        let inside = below_50.iter().any(|range| range.contains(&soil));
+        assert_eq!(soil_to_humidity.lookup(soil) < 50, inside);
+    }
+
+    // the lowest location of part 1 comes from seed 13
+    assert_eq!(vec![13], map_set.inverse(LOCATION, SEED, 35).unwrap());
+}
+
+#[test]
+fn category_graph() {
+    let input = std::fs::read_to_string("test1.txt").unwrap();
+    let mut blocks = input.split("\n\n").skip(1).collect::<Vec<_>>();
+    let parse = |blocks: &[&str]| get_map(blocks.join("\n\n").lines().map(String::from));
+
+    let expected = parse(&blocks).unwrap().compose().unwrap();
+    blocks.reverse();
+    let shuffled = parse(&blocks).unwrap();
+    assert_eq!(expected, shuffled.compose().unwrap());
+    assert_eq!(
+        Piecewise::default(),
+        shuffled.compose_between("water", "water").unwrap()
+    );
+
+    assert_eq!(
+        Err("broken chain: no maps lead between the categories"),
+        shuffled.compose_between(LOCATION, SEED)
+    );
+    assert_eq!(
+        Err("unknown category"),
+        shuffled.compose_between(SEED, "fuel")
+    );
+
+    let mut broken = blocks.clone();
+    broken.remove(3);
+    assert_eq!(
+        Err("broken chain: no maps lead between the categories"),
+        parse(&broken).unwrap().compose()
+    );
+
+    let mut ambiguous = blocks.clone();
+    ambiguous.push("seed-to-carrot map:\n1 2 3");
+    assert_eq!(
+        Err("ambiguous maps: a category is mapped from twice"),
+        parse(&ambiguous).map(|_| ())
+    );
+
+    let cyclic = [
+        "a-to-b map:\n1 2 3",
+        "b-to-a map:\n1 2 3",
+        "c-to-d map:\n1 2 3",
+    ];
+    assert_eq!(
+        Err("broken chain: the maps form a cycle"),
+        parse(&cyclic)
+            .unwrap()
+            .compose_between("a", "c")
+            .map(|_| ())
+    );
+}
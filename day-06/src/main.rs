@@ -6,7 +6,8 @@ const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-06 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-06 p<n> \
+                 | boat <file> <linear:<g>|capped:<g>,<max>|decaying:<g>,<d>> [merged]";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -17,6 +18,42 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "boat" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let (file, spec, merged) = match args.as_slice() {
+                    [file, spec] => (file, spec, false),
+                    [file, spec, merged] if merged == "merged" => (file, spec, true),
+                    _ => {
+                        eprintln!("{usage}");
+                        return;
+                    }
+                };
+                let boat = match Boat::new(spec) {
+                    Ok(boat) => boat,
+                    Err(e) => {
+                        eprintln!("An error occurred:\n{e}");
+                        return;
+                    }
+                };
+
+                let mut product = 1;
+                for race in get_races(file, merged) {
+                    match race.winning_window(&boat) {
+                        Some(window) => {
+                            println!("{}ms, {}mm: {window:?}", race.time, race.distance);
+                            product *= window.end - window.start;
+                        }
+                        None => {
+                            println!(
+                                "{}ms, {}mm: no winning hold times",
+                                race.time, race.distance
+                            );
+                            product = 0;
+                        }
+                    }
+                }
+                println!("Product is {product}");
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -24,7 +61,7 @@ fn main() {
     }
 }
 
-fn get_nums(line: &str) -> Vec<usize> {
+fn get_nums(line: &str) -> Vec<u128> {
     let (_, times) = line.split_once(':').unwrap();
     times
         .split_ascii_whitespace()
@@ -32,51 +69,204 @@ fn get_nums(line: &str) -> Vec<usize> {
         .collect()
 }
 
-fn get_nums_merged(line: &str) -> usize {
+fn get_nums_merged(line: &str) -> u128 {
     let (_, times) = line.split_once(':').unwrap();
     let times: String = times.split_ascii_whitespace().collect();
     times.parse().unwrap()
 }
 
-fn part1(filename: &str) -> usize {
+fn get_races(filename: &str, merged: bool) -> Vec<Race> {
     let file = File::open(filename).expect("Should be able to read the file");
     let file = BufReader::new(file);
 
     let mut lines = file.lines();
-    let times = get_nums(&lines.next().unwrap().unwrap());
-    let distances = get_nums(&lines.next().unwrap().unwrap());
+    let times = lines.next().unwrap().unwrap();
+    let distances = lines.next().unwrap().unwrap();
 
-    times
-        .iter()
-        .zip(distances)
-        .fold(1, |product, (time, distance)| {
-            let options = (1..*time).into_iter().fold(0, |count, t| {
-                if t * (time - t) > distance {
-                    count + 1
+    if merged {
+        vec![Race {
+            time: get_nums_merged(&times),
+            distance: get_nums_merged(&distances),
+        }]
+    } else {
+        get_nums(&times)
+            .into_iter()
+            .zip(get_nums(&distances))
+            .map(|(time, distance)| Race { time, distance })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Boat {
+    // every ms of holding adds `gain` mm/ms
+    Linear { gain: u128 },
+    // like `Linear`, but the speed never exceeds `max`
+    Capped { gain: u128, max: u128 },
+    // the first ms adds `gain`, every following one `decay` less than the one before
+    Decaying { gain: u128, decay: u128 },
+}
+
+impl Boat {
+    fn new(spec: &str) -> Result<Self, &'static str> {
+        let (name, params) = match spec.split_once(':') {
+            Some(split) => split,
+            None => return Err("no `:` found in Boat"),
+        };
+        let params = params
+            .split(',')
+            .map(|param| match param.parse() {
+                Ok(param) => Ok(param),
+                Err(_) => Err("couldn't parse parameter in Boat"),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match (name, params.as_slice()) {
+            ("linear", &[gain]) => Ok(Self::Linear { gain }),
+            ("capped", &[gain, max]) => Ok(Self::Capped { gain, max }),
+            ("decaying", &[gain, decay]) => Ok(Self::Decaying { gain, decay }),
+            _ => Err("unknown Boat"),
+        }
+    }
+
+    // `None` when the speed doesn't fit into a u128
+    fn speed(&self, hold: u128) -> Option<u128> {
+        match *self {
+            Self::Linear { gain } => gain.checked_mul(hold),
+            Self::Capped { gain, max } => Some(gain.checked_mul(hold).map_or(max, |s| s.min(max))),
+            Self::Decaying { gain, decay } => {
+                // only the first `boosts` ms add any speed
+                let boosts = match decay {
+                    0 => hold,
+                    _ => hold.min(gain.div_ceil(decay)),
+                };
+                if boosts == 0 {
+                    return Some(0);
+                }
+
+                // an arithmetic series from `gain` down to `last`, where `decay * (boosts - 1)`
+                // stays below `gain` as long as every boost adds speed
+                let last = gain - decay * (boosts - 1);
+                if boosts % 2 == 1 {
+                    boosts.checked_mul(last + decay * (boosts - 1) / 2)
                 } else {
-                    count
+                    (boosts / 2).checked_mul(gain.checked_add(last)?)
                 }
-            });
+            }
+        }
+    }
+}
 
-            product * options
-        })
+// first value in `range` for which `pred` is false, `pred` has to be true up to there
+fn partition_point(range: std::ops::Range<u128>, pred: impl Fn(u128) -> bool) -> u128 {
+    let (mut low, mut high) = (range.start, range.end);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
 }
 
-fn part2(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+struct Race {
+    time: u128,
+    distance: u128,
+}
 
-    let mut lines = file.lines();
-    let time = get_nums_merged(&lines.next().unwrap().unwrap());
-    let distance = get_nums_merged(&lines.next().unwrap().unwrap());
+impl Race {
+    // `hold * (time - hold) > distance`, without multiplying so that it can't overflow
+    fn beats_record(&self, hold: u128) -> bool {
+        hold > 0 && hold < self.time && self.time - hold > self.distance / hold
+    }
 
-    (1..time).into_iter().fold(0, |count, t| {
-        if t * (time - t) > distance {
-            count + 1
-        } else {
-            count
+    // `None` when the distance doesn't fit into a u128, so it beats any record
+    fn travelled(&self, boat: &Boat, hold: u128) -> Option<u128> {
+        match self.time - hold {
+            0 => Some(0),
+            remaining => boat.speed(hold)?.checked_mul(remaining),
         }
-    })
+    }
+
+    fn beats_with(&self, boat: &Boat, hold: u128) -> bool {
+        self.travelled(boat, hold)
+            .is_none_or(|distance| distance > self.distance)
+    }
+
+    // all boats have a travelled distance that rises up to a peak and then falls,
+    // so the winning hold times form at most one window
+    fn winning_window(&self, boat: &Boat) -> Option<std::ops::Range<u128>> {
+        // overflowing distances compare as larger than any other
+        let rises = |hold| match (self.travelled(boat, hold + 1), self.travelled(boat, hold)) {
+            (None, Some(_)) => true,
+            (Some(next), Some(curr)) => next > curr,
+            _ => false,
+        };
+        let peak = partition_point(0..self.time, rises);
+        if !self.beats_with(boat, peak) {
+            return None;
+        }
+
+        let first = partition_point(0..peak, |hold| !self.beats_with(boat, hold));
+        // holding for all of `time` travels nothing and never wins, so the search can stop
+        // short of it, which keeps `time + 1` from overflowing at u128::MAX
+        let end = partition_point(peak..self.time, |hold| self.beats_with(boat, hold));
+
+        Some(first..end)
+    }
+
+    // counts the hold times `t` with `t * (time - t) > distance`, the roots of
+    // `t^2 - time * t + distance` are `(time +- sqrt(time^2 - 4 * distance)) / 2`.
+    // past 2^64 `time^2` overflows u128, so there the first winning hold is binary
+    // searched instead, which works because `t * (time - t)` rises up to `time / 2`
+    fn winning_count(&self) -> u128 {
+        let half = self.time / 2;
+        let first = match self.time.checked_mul(self.time) {
+            Some(square) => {
+                // `4 * distance` only overflows when it is larger than `time^2` anyway
+                let discriminant = match self
+                    .distance
+                    .checked_mul(4)
+                    .and_then(|distance| square.checked_sub(distance))
+                {
+                    Some(d) => d,
+                    None => return 0,
+                };
+
+                // the integer root can be off by one from the real root, so step onto
+                // the first winning hold time from either side
+                let mut first = (self.time - discriminant.isqrt()) / 2;
+                while first <= half && !self.beats_record(first) {
+                    first += 1;
+                }
+                while first > 0 && self.beats_record(first - 1) {
+                    first -= 1;
+                }
+                first
+            }
+            None => partition_point(0..half + 1, |hold| !self.beats_record(hold)),
+        };
+        if first > half {
+            return 0;
+        }
+
+        // winning hold times are symmetric around `time / 2`
+        let last = self.time - first;
+        last - first + 1
+    }
+}
+
+fn part1(filename: &str) -> u128 {
+    get_races(filename, false)
+        .iter()
+        .map(Race::winning_count)
+        .product()
+}
+
+fn part2(filename: &str) -> u128 {
+    get_races(filename, true)[0].winning_count()
 }
 
 #[test]
@@ -98,3 +288,169 @@ fn part2_example() {
 fn part2_puzzle() {
     assert_eq!(35150181, part2(PART2_FILE));
 }
+
+#[test]
+fn winning_count_matches_brute_force() {
+    let brute_force =
+        |race: &Race| (1..race.time).filter(|&t| race.beats_record(t)).count() as u128;
+
+    // xorshift, so that the inputs are random but reproducible
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |max: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % max) as u128
+    };
+
+    for _ in 0..2_000 {
+        let time = next(300);
+        let distance = next((time * time / 4 + 5) as u64);
+        let race = Race { time, distance };
+        assert_eq!(
+            brute_force(&race),
+            race.winning_count(),
+            "{time} {distance}"
+        );
+    }
+
+    // perfect squares, where the record can only be tied
+    for time in 0..100 {
+        let race = Race {
+            time,
+            distance: time * time / 4,
+        };
+        assert_eq!(brute_force(&race), race.winning_count());
+    }
+
+    // large enough that `f64` can not represent the times exactly
+    let race = Race {
+        time: 1 << 62,
+        distance: (1 << 122) - 2,
+    };
+    assert_eq!(3, race.winning_count());
+
+    // past 2^64 `time * time` overflows, but holds near the peak still multiply fine
+    let time: u128 = (1 << 64) + 2;
+    let half = time / 2;
+    for margin in [0, 1, 5, 100, 1_000] {
+        let race = Race {
+            time,
+            distance: half * (time - half) - margin,
+        };
+        let brute_force = (half - 100..=half + 100)
+            .filter(|&t| t * (time - t) > race.distance)
+            .count() as u128;
+        assert_eq!(brute_force, race.winning_count(), "{margin}");
+    }
+    assert_eq!(time - 1, Race { time, distance: 0 }.winning_count());
+
+    let race = Race {
+        time: u128::MAX,
+        distance: u128::MAX - 1,
+    };
+    assert_eq!(u128::MAX - 3, race.winning_count());
+}
+
+#[test]
+fn winning_windows_match_brute_force() {
+    let boats = [
+        Boat::Linear { gain: 1 },
+        Boat::Linear { gain: 3 },
+        Boat::Linear { gain: 0 },
+        Boat::Capped { gain: 2, max: 9 },
+        Boat::Capped { gain: 1, max: 0 },
+        Boat::Decaying { gain: 10, decay: 3 },
+        Boat::Decaying { gain: 4, decay: 0 },
+    ];
+
+    for boat in &boats {
+        for time in 0..40 {
+            for distance in 0..120 {
+                let race = Race { time, distance };
+                let winning = (0..=time)
+                    .filter(|&hold| race.travelled(boat, hold).unwrap() > distance)
+                    .collect::<Vec<_>>();
+                let found = race
+                    .winning_window(boat)
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                assert_eq!(winning, found, "{boat:?} {time} {distance}");
+            }
+        }
+    }
+
+    for race in get_races("test1.txt", false) {
+        let window = race.winning_window(&Boat::Linear { gain: 1 }).unwrap();
+        assert_eq!(race.winning_count(), window.end - window.start);
+    }
+    assert_eq!(
+        Some(2..6),
+        get_races("test1.txt", false)[0].winning_window(&Boat::Linear { gain: 1 })
+    );
+    assert_eq!(
+        Ok(Boat::Capped { gain: 2, max: 9 }),
+        Boat::new("capped:2,9")
+    );
+    assert_eq!(Err("unknown Boat"), Boat::new("capped:2"));
+}
+
+#[test]
+fn winning_windows_overflow() {
+    // every hold but the first and last travels more than u128 can hold
+    let race = Race {
+        time: 10,
+        distance: u128::MAX,
+    };
+    assert_eq!(None, race.travelled(&Boat::Linear { gain: 1 << 126 }, 1));
+    assert_eq!(
+        Some(1..10),
+        race.winning_window(&Boat::Linear { gain: 1 << 126 })
+    );
+    assert_eq!(
+        None,
+        race.winning_window(&Boat::Capped {
+            gain: 1 << 126,
+            max: u128::MAX / 10
+        })
+    );
+
+    // one boost of `gain` is all the speed there is, whatever the hold time
+    let boat = Boat::Decaying {
+        gain: u128::MAX,
+        decay: u128::MAX,
+    };
+    assert_eq!(Some(u128::MAX), boat.speed(5));
+    let race = Race {
+        time: 3,
+        distance: u128::MAX - 1,
+    };
+    assert_eq!(Some(1..3), race.winning_window(&boat));
+
+    // `gain + last` overflows, and so does the speed
+    let boat = Boat::Decaying {
+        gain: u128::MAX - 1,
+        decay: 2,
+    };
+    assert_eq!(Some(u128::MAX - 1), boat.speed(1));
+    assert_eq!(None, boat.speed(2));
+
+    // near the peak the linear boat matches the plain race past 2^64 too
+    let race = Race {
+        time: (1 << 64) + 2,
+        distance: (1 << 126) + (1 << 64) - 100,
+    };
+    let window = race.winning_window(&Boat::Linear { gain: 1 }).unwrap();
+    assert_eq!(race.winning_count(), window.end - window.start);
+
+    let race = Race {
+        time: u128::MAX,
+        distance: 0,
+    };
+    assert_eq!(
+        Some(1..u128::MAX),
+        race.winning_window(&Boat::Linear { gain: 1 })
+    );
+    assert_eq!(u128::MAX - 1, race.winning_count());
+}
@@ -8,7 +8,9 @@ const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-07 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-07 p<n> \
+                 | rules <file> <order> <wild> <hand size> <dealt|strongest> \
+                 | ranking <file> <p1|p2> | explain <cards>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -19,6 +21,64 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "rules" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let winnings = match args.as_slice() {
+                    [file, order, wild, hand_size, tie_break] => {
+                        let hand_size = hand_size.parse().map_err(|_| "couldn't parse hand size");
+                        let tie_break = match tie_break.as_str() {
+                            "dealt" => Ok(TieBreak::DealtOrder),
+                            "strongest" => Ok(TieBreak::Strongest),
+                            _ => Err("unknown tie-break policy"),
+                        };
+                        hand_size
+                            .and_then(|size| tie_break.map(|tie_break| (size, tie_break)))
+                            .and_then(|(size, tie_break)| Rules::new(order, wild, size, tie_break))
+                            .and_then(|rules| total_winnings(file, &rules))
+                    }
+                    _ => Err(usage),
+                };
+                match winnings {
+                    Ok(winnings) => println!("Sum is {winnings}"),
+                    Err(e) => eprintln!("An error occurred:\n{e}"),
+                }
+            }
+            "ranking" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let hands = match args.as_slice() {
+                    [file, part] if part == "p1" => rank_hands(file, &Rules::part1()),
+                    [file, part] if part == "p2" => rank_hands(file, &Rules::part2()),
+                    _ => Err(usage),
+                };
+                match hands {
+                    Ok(hands) => {
+                        println!("rank,hand,type,bid,winnings");
+                        for (i, hand) in hands.iter().enumerate() {
+                            let rank = i + 1;
+                            println!(
+                                "{rank},{},{:?},{},{}",
+                                hand.label,
+                                hand.poker_type,
+                                hand.bid,
+                                rank * hand.bid
+                            );
+                        }
+                    }
+                    Err(e) => eprintln!("An error occurred:\n{e}"),
+                }
+            }
+            "explain" => match env::args().nth(2) {
+                Some(cards) => match Explanation::new(&cards, &Rules::part2()) {
+                    Ok(explanation) => {
+                        for (position, card) in &explanation.substitutions {
+                            println!("card {} plays as {card}", position + 1);
+                        }
+                        println!("{} is {:?}", explanation.hand, explanation.poker_type);
+                    }
+                    Err(e) => eprintln!("An error occurred:\n{e}"),
+                },
+                None => eprintln!("{usage}"),
+            },
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -26,41 +86,69 @@ fn main() {
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Hash)]
-enum Card {
-    _A = 13,
-    _K = 12,
-    _Q = 11,
-    _J = 10,
-    _T = 9,
-    _9 = 8,
-    _8 = 7,
-    _7 = 6,
-    _6 = 5,
-    _5 = 4,
-    _4 = 3,
-    _3 = 2,
-    _2 = 1,
-    _J2 = 0,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TieBreak {
+    // compare the cards in the order they were dealt
+    DealtOrder,
+    // compare the strongest cards first, like in poker
+    Strongest,
 }
 
-const CHAR_TO_CARD_LIST: [(char, Card); 13] = [
-    ('A', Card::_A),
-    ('K', Card::_K),
-    ('Q', Card::_Q),
-    ('J', Card::_J),
-    ('T', Card::_T),
-    ('9', Card::_9),
-    ('8', Card::_8),
-    ('7', Card::_7),
-    ('6', Card::_6),
-    ('5', Card::_5),
-    ('4', Card::_4),
-    ('3', Card::_3),
-    ('2', Card::_2),
-];
-
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug)]
+struct Rules {
+    // all cards, from the weakest to the strongest
+    order: Vec<char>,
+    wild: Vec<char>,
+    hand_size: usize,
+    tie_break: TieBreak,
+}
+
+impl Rules {
+    fn new(
+        order: &str,
+        wild: &str,
+        hand_size: usize,
+        tie_break: TieBreak,
+    ) -> Result<Self, &'static str> {
+        let order: Vec<char> = order.chars().collect();
+        let wild: Vec<char> = wild.chars().collect();
+
+        if order
+            .iter()
+            .enumerate()
+            .any(|(i, card)| order[i + 1..].contains(card))
+        {
+            return Err("duplicate card in ranking order");
+        }
+        if !wild.iter().all(|card| order.contains(card)) {
+            return Err("wild card is not in the ranking order");
+        }
+        if hand_size == 0 {
+            return Err("hand size has to be at least 1");
+        }
+
+        Ok(Self {
+            order,
+            wild,
+            hand_size,
+            tie_break,
+        })
+    }
+
+    fn part1() -> Self {
+        Self::new("23456789TJQKA", "", 5, TieBreak::DealtOrder).expect("rules are valid")
+    }
+
+    fn part2() -> Self {
+        Self::new("J23456789TQKA", "J", 5, TieBreak::DealtOrder).expect("rules are valid")
+    }
+
+    fn strength(&self, card: char) -> Option<usize> {
+        self.order.iter().position(|c| *c == card)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
 enum PokerType {
     Quintuplet = 6,
     Quadruplet = 5,
@@ -71,96 +159,151 @@ enum PokerType {
     HighCard = 0,
 }
 
-#[derive(Debug)]
-struct Hand {
-    cards: Vec<Card>,
+impl PokerType {
+    // wild cards always join the largest group of equal cards
+    fn classify(cards: &str, wild: &[char]) -> Self {
+        let mut card_to_count = HashMap::with_capacity(cards.len());
+        let mut wild_count = 0;
+        for card in cards.chars() {
+            if wild.contains(&card) {
+                wild_count += 1;
+            } else {
+                *card_to_count.entry(card).or_insert(0) += 1;
+            }
+        }
+
+        let mut counts: Vec<usize> = card_to_count.into_values().collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        match counts.first_mut() {
+            Some(largest) => *largest += wild_count,
+            None => counts.push(wild_count),
+        }
+
+        match (counts[0], counts.get(1).copied().unwrap_or(0)) {
+            (5.., _) => Self::Quintuplet,
+            (4, _) => Self::Quadruplet,
+            (3, 2..) => Self::FullHouse,
+            (3, _) => Self::Triplet,
+            (2, 2..) => Self::TwoPair,
+            (2, _) => Self::Pair,
+            _ => Self::HighCard,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Explanation {
+    // (position in the hand, card the wild card stands in for)
+    substitutions: Vec<(usize, char)>,
+    hand: String,
     poker_type: PokerType,
-    bid: usize,
 }
 
-impl Hand {
-    fn new(line: &str, map: &HashMap<char, Card>) -> Self {
-        let (cards, bid) = line.split_once(' ').unwrap();
+impl Explanation {
+    // tries every other card for every wild card and keeps the best hand,
+    // preferring stronger substitutes when several give the same type
+    fn new(cards: &str, rules: &Rules) -> Result<Self, &'static str> {
+        if cards.chars().count() != rules.hand_size {
+            return Err("wrong number of cards in Hand");
+        }
+        if cards.chars().any(|card| rules.strength(card).is_none()) {
+            return Err("unknown card in Hand");
+        }
 
-        let cards: Vec<Card> = cards.chars().map(|c| *map.get(&c).unwrap()).collect();
-        let bid = bid.parse().unwrap();
+        let positions: Vec<usize> = cards
+            .chars()
+            .enumerate()
+            .filter(|(_, card)| rules.wild.contains(card))
+            .map(|(position, _)| position)
+            .collect();
+        let substitutes: Vec<char> = rules
+            .order
+            .iter()
+            .rev()
+            .filter(|card| !rules.wild.contains(card))
+            .copied()
+            .collect();
 
-        Self {
-            cards,
-            poker_type: PokerType::HighCard,
-            bid,
+        let mut hand: Vec<char> = cards.chars().collect();
+        let mut best = None;
+        if !substitutes.is_empty() {
+            Self::substitute(&mut hand, &positions, &substitutes, &mut best);
         }
+
+        // without any other cards the wild cards can only stand for themselves
+        let (hand, poker_type) = best.unwrap_or((hand, PokerType::classify(cards, &[])));
+        Ok(Self {
+            substitutions: positions.iter().map(|&p| (p, hand[p])).collect(),
+            hand: hand.into_iter().collect(),
+            poker_type,
+        })
     }
 
-    fn parse_hand_type(&mut self) {
-        let mut card_to_count = HashMap::with_capacity(self.cards.len());
-        self.cards
-            .iter()
-            .for_each(|card| *card_to_count.entry(card).or_insert(0) += 1);
-
-        self.poker_type = match card_to_count.len() {
-            1 => PokerType::Quintuplet,
-            2 => {
-                if *card_to_count.values().max().unwrap() == 4 {
-                    PokerType::Quadruplet
-                } else {
-                    PokerType::FullHouse
-                }
+    fn substitute(
+        hand: &mut Vec<char>,
+        positions: &[usize],
+        substitutes: &[char],
+        best: &mut Option<(Vec<char>, PokerType)>,
+    ) {
+        let Some((&position, rest)) = positions.split_first() else {
+            let poker_type = PokerType::classify(&hand.iter().collect::<String>(), &[]);
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_type)| poker_type > *best_type)
+            {
+                *best = Some((hand.clone(), poker_type));
             }
-            3 => {
-                if *card_to_count.values().max().unwrap() == 3 {
-                    PokerType::Triplet
-                } else {
-                    PokerType::TwoPair
-                }
-            }
-            4 => PokerType::Pair,
-            5 => PokerType::HighCard,
-            _ => unreachable!("there are no other options"),
+            return;
         };
+
+        for &card in substitutes {
+            hand[position] = card;
+            Self::substitute(hand, rest, substitutes, best);
+        }
     }
+}
 
-    fn optimize_hand_type(&mut self) {
-        self.parse_hand_type();
+#[derive(Debug)]
+struct Hand {
+    label: String,
+    // card strengths, ordered by the tie-break policy
+    cards: Vec<usize>,
+    poker_type: PokerType,
+    bid: usize,
+}
 
-        let joker_count = self.cards.iter().filter(|c| **c == Card::_J).count();
-        if joker_count == 0 {
-            return;
-        }
-        self.cards.iter_mut().for_each(|c| {
-            if *c == Card::_J {
-                *c = Card::_J2;
-            }
-        });
+impl Hand {
+    fn new(line: &str, rules: &Rules) -> Result<Self, &'static str> {
+        let (cards, bid) = match line.split_once(' ') {
+            Some(split) => split,
+            None => return Err("no ` ` found in Hand"),
+        };
 
-        match self.poker_type {
-            PokerType::Quintuplet => { /* already the best */ }
-            PokerType::Quadruplet | PokerType::FullHouse => {
-                // make a quintuplet from quadruplet or full house
-                self.poker_type = PokerType::Quintuplet;
-            }
-            PokerType::Triplet => {
-                // make a quadruplet
-                self.poker_type = PokerType::Quadruplet;
-            }
-            PokerType::TwoPair => {
-                if joker_count == 2 {
-                    // join the other pair
-                    self.poker_type = PokerType::Quadruplet;
-                } else {
-                    // join one of the pairs
-                    self.poker_type = PokerType::FullHouse;
-                }
-            }
-            PokerType::Pair => {
-                // make a triplet by joining the best other card
-                self.poker_type = PokerType::Triplet;
-            }
-            PokerType::HighCard => {
-                // either make a pair with the highest other card
-                self.poker_type = PokerType::Pair;
-            }
+        if cards.chars().count() != rules.hand_size {
+            return Err("wrong number of cards in Hand");
         }
+        let mut strengths = cards
+            .chars()
+            .map(|card| match rules.strength(card) {
+                Some(strength) => Ok(strength),
+                None => Err("unknown card in Hand"),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if rules.tie_break == TieBreak::Strongest {
+            strengths.sort_unstable_by(|a, b| b.cmp(a));
+        }
+
+        let bid = match bid.parse() {
+            Ok(bid) => bid,
+            Err(_) => return Err("couldn't parse bid in Hand"),
+        };
+
+        Ok(Self {
+            label: cards.to_string(),
+            cards: strengths,
+            poker_type: PokerType::classify(cards, &rules.wild),
+            bid,
+        })
     }
 }
 
@@ -171,16 +314,7 @@ impl Ord for Hand {
             return rank_ord;
         }
 
-        if let Some((card, other_card)) = self
-            .cards
-            .iter()
-            .zip(&other.cards)
-            .find(|(card, other_card)| card != other_card)
-        {
-            card.cmp(other_card)
-        } else {
-            Ordering::Equal
-        }
+        self.cards.cmp(&other.cards)
     }
 }
 
@@ -190,54 +324,45 @@ impl PartialOrd for Hand {
     }
 }
 
+// hands are equal when neither ranks above the other, the bid does not matter
 impl PartialEq for Hand {
     fn eq(&self, other: &Self) -> bool {
-        self.poker_type == other.poker_type
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl Eq for Hand {}
 
-fn part1(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+// sorted from the lowest to the highest rank, tied hands keep their input order
+fn rank_hands(filename: &str, rules: &Rules) -> Result<Vec<Hand>, &'static str> {
+    let file = match File::open(filename) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return Err("could not open file"),
+    };
 
-    let char_to_card = HashMap::from(CHAR_TO_CARD_LIST);
-    let mut hands = Vec::new();
+    let mut hands = file
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| Hand::new(&line, rules))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    for line in file.lines() {
-        let line = line.unwrap();
-        let mut hand = Hand::new(&line, &char_to_card);
-        hand.parse_hand_type();
-        hands.push(hand);
-    }
+    hands.sort();
+    Ok(hands)
+}
 
-    hands.sort_unstable();
-    hands
+fn total_winnings(filename: &str, rules: &Rules) -> Result<usize, &'static str> {
+    Ok(rank_hands(filename, rules)?
         .iter()
         .enumerate()
-        .fold(0, |sum, (i, hand)| sum + (i + 1) * hand.bid)
+        .fold(0, |sum, (i, hand)| sum + (i + 1) * hand.bid))
 }
 
-fn part2(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
-
-    let char_to_card = HashMap::from(CHAR_TO_CARD_LIST);
-    let mut hands = Vec::new();
-
-    for line in file.lines() {
-        let line = line.unwrap();
-        let mut hand = Hand::new(&line, &char_to_card);
-        hand.optimize_hand_type();
-        hands.push(hand);
-    }
+fn part1(filename: &str) -> usize {
+    total_winnings(filename, &Rules::part1()).expect("Should be able to parse the hands")
+}
 
-    hands.sort_unstable();
-    hands
-        .iter()
-        .enumerate()
-        .fold(0, |sum, (i, hand)| sum + (i + 1) * hand.bid)
+fn part2(filename: &str) -> usize {
+    total_winnings(filename, &Rules::part2()).expect("Should be able to parse the hands")
 }
 
 #[test]
@@ -259,3 +384,127 @@ fn part2_example() {
 fn part2_puzzle() {
     assert_eq!(250506580, part2(PART2_FILE));
 }
+
+#[test]
+fn classify_with_rules() {
+    let rules = Rules::new("23456789TJQKA", "J", 5, TieBreak::Strongest).unwrap();
+    let classify = |cards| PokerType::classify(cards, &rules.wild);
+
+    assert_eq!(PokerType::Quintuplet, classify("JJJJJ"));
+    assert_eq!(PokerType::Quintuplet, classify("AJJJA"));
+    assert_eq!(PokerType::Quadruplet, classify("KTJJT"));
+    assert_eq!(PokerType::FullHouse, classify("KKJQQ"));
+    assert_eq!(PokerType::Triplet, classify("2J245"));
+    assert_eq!(PokerType::TwoPair, classify("22334"));
+    assert_eq!(PokerType::Pair, classify("J2345"));
+    assert_eq!(PokerType::HighCard, classify("23456"));
+
+    let hand = Hand::new("29A9T 1", &rules).unwrap();
+    assert_eq!(vec![12, 8, 7, 7, 0], hand.cards);
+
+    assert_eq!(
+        Err("wild card is not in the ranking order"),
+        Rules::new("AKQ", "J", 5, TieBreak::DealtOrder).map(|_| ())
+    );
+    assert_eq!(
+        Err("duplicate card in ranking order"),
+        Rules::new("AKA", "", 5, TieBreak::DealtOrder).map(|_| ())
+    );
+
+    let rules = Rules::new("abc", "c", 3, TieBreak::DealtOrder).unwrap();
+    assert_eq!(
+        PokerType::Triplet,
+        Hand::new("acc 1", &rules).unwrap().poker_type
+    );
+    assert_eq!(
+        Err("wrong number of cards in Hand"),
+        Hand::new("aa 1", &rules).map(|_| ())
+    );
+    assert_eq!(
+        Err("unknown card in Hand"),
+        Hand::new("aad 1", &rules).map(|_| ())
+    );
+}
+
+#[test]
+fn hand_ordering() {
+    let rules = Rules::part2();
+    let hand = |line| Hand::new(line, &rules).unwrap();
+
+    // equal exactly when neither ranks above the other
+    let hands = [
+        "KTJJT 1", "KTJJT 2", "QQQJA 3", "T55J5 4", "JJJJJ 5", "22222 6",
+    ]
+    .map(hand);
+    for a in &hands {
+        for b in &hands {
+            assert_eq!(a.cmp(b) == Ordering::Equal, a == b);
+        }
+    }
+    assert!(hands[0] == hands[1]);
+    assert!(hands[2] != hands[3]);
+    assert!(hands[4] < hands[5]);
+
+    // tied hands keep their input order
+    let mut ranked = ["KTJJT 9", "32T3K 1", "KTJJT 2"].map(hand);
+    ranked.sort();
+    assert_eq!(
+        vec![1, 9, 2],
+        ranked.iter().map(|h| h.bid).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn explain_substitutions() {
+    let rules = Rules::part2();
+
+    let explanation = Explanation::new("KTJJT", &rules).unwrap();
+    assert_eq!(vec![(2, 'T'), (3, 'T')], explanation.substitutions);
+    assert_eq!("KTTTT", explanation.hand);
+    assert_eq!(PokerType::Quadruplet, explanation.poker_type);
+
+    let explanation = Explanation::new("JJJJJ", &rules).unwrap();
+    assert_eq!("AAAAA", explanation.hand);
+    let explanation = Explanation::new("2345J", &rules).unwrap();
+    assert_eq!(
+        ("23455".to_string(), PokerType::Pair),
+        (explanation.hand, explanation.poker_type)
+    );
+    let explanation = Explanation::new("23456", &rules).unwrap();
+    assert_eq!(Vec::<(usize, char)>::new(), explanation.substitutions);
+
+    assert_eq!(
+        Err("unknown card in Hand"),
+        Explanation::new("2345X", &rules)
+    );
+}
+
+#[test]
+fn classify_matches_brute_force() {
+    // every multiset of 5 cards, as the classification ignores the card order
+    fn multisets(order: &[char], size: usize) -> Vec<String> {
+        match (order.split_first(), size) {
+            (_, 0) => vec![String::new()],
+            (None, _) => vec![],
+            (Some((&card, rest)), _) => {
+                let mut with_card = multisets(order, size - 1);
+                with_card.iter_mut().for_each(|hand| hand.insert(0, card));
+                with_card.extend(multisets(rest, size));
+                with_card
+            }
+        }
+    }
+
+    let rules = Rules::part2();
+    let hands = multisets(&rules.order, rules.hand_size);
+    assert_eq!(6188, hands.len());
+
+    for hand in hands {
+        let explanation = Explanation::new(&hand, &rules).unwrap();
+        assert_eq!(
+            explanation.poker_type,
+            PokerType::classify(&hand, &rules.wild),
+            "{hand}"
+        );
+    }
+}
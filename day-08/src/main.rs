@@ -1,7 +1,8 @@
-use num::integer;
+use num::Integer;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader};
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
@@ -15,8 +16,10 @@ const END_LABEL2: &str = "Z";
 const RIGHT: u32 = 0;
 const LEFT: u32 = 1;
 
+const OVERLAY_COLORS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-08 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-08 p<n> | dot <file> [overlay]";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -27,6 +30,27 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "dot" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let (file, overlay) = match args.as_slice() {
+                    [file] => (file, false),
+                    [file, overlay] if overlay == "overlay" => (file, true),
+                    _ => {
+                        eprintln!("{usage}");
+                        return;
+                    }
+                };
+                match get_network(file) {
+                    Ok((command_line, network)) => {
+                        let paths = match overlay {
+                            true => ghost_paths(&network, &command_line),
+                            false => vec![],
+                        };
+                        print!("{}", network.to_dot(&paths));
+                    }
+                    Err(e) => eprintln!("An error occurred:\n{e}"),
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -36,113 +60,169 @@ fn main() {
 
 struct Node {
     label: String,
-    left: Option<usize>,
-    right: Option<usize>,
-    is_end: bool,
+    left: usize,
+    right: usize,
+}
+
+struct Network {
+    nodes: Vec<Node>,
+    indices: HashMap<String, usize>,
 }
 
-impl Node {
-    fn new(label: &str, end_str: &str) -> Self {
-        let label = label.to_string();
-        let is_end = label.ends_with(end_str);
+impl Network {
+    fn new(lines: impl Iterator<Item = String>) -> Result<Self, &'static str> {
+        let mut indices = HashMap::new();
+        let mut labels = vec![];
+        let mut links = vec![];
+
+        // gives every label an index the first time it is seen, defined or not
+        let mut index_of = |label: &str, links: &mut Vec<Option<(usize, usize)>>| {
+            *indices.entry(label.to_string()).or_insert_with(|| {
+                labels.push(label.to_string());
+                links.push(None);
+                links.len() - 1
+            })
+        };
+
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
 
-        Self {
-            label,
-            left: None,
-            right: None,
-            is_end,
+            let (label, connections) = match line.split_once(" = ") {
+                Some(split) => split,
+                None => return Err("no ` = ` found in Node"),
+            };
+            let (left, right) = match connections
+                .strip_prefix('(')
+                .and_then(|conn| conn.strip_suffix(')'))
+                .and_then(|conn| conn.split_once(", "))
+            {
+                Some(split) => split,
+                None => return Err("no `(<left>, <right>)` found in Node"),
+            };
+
+            let node = index_of(label, &mut links);
+            if links[node].is_some() {
+                return Err("Node is defined twice");
+            }
+            let left = index_of(left, &mut links);
+            let right = index_of(right, &mut links);
+            links[node] = Some((left, right));
         }
+
+        let nodes = labels
+            .into_iter()
+            .zip(links)
+            .map(|(label, link)| match link {
+                Some((left, right)) => Ok(Node { label, left, right }),
+                None => Err("Node references an undefined label"),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { nodes, indices })
     }
 
-    fn with_left_right(
-        label: String,
-        left: Option<usize>,
-        right: Option<usize>,
-        is_end: bool,
-    ) -> Self {
-        Self {
-            label,
-            left,
-            right,
-            is_end,
-        }
+    fn get(&self, label: &str) -> Option<usize> {
+        self.indices.get(label).copied()
     }
-}
 
-fn get_commands(lines: &mut Lines<BufReader<File>>) -> Vec<u32> {
-    let command_line = lines.next().unwrap().unwrap();
-    command_line
-        .chars()
-        .map(|c| match c {
-            'R' => RIGHT,
-            'L' => LEFT,
-            _ => unreachable!("no other commands"),
-        })
-        .collect()
-}
+    // L/R edges, start and end nodes are filled in, every path gets its own color
+    fn to_dot(&self, paths: &[Vec<(usize, u32)>]) -> String {
+        let mut dot = String::from("digraph network {\n");
 
-fn get_nodes_conns(
-    lines: &mut Lines<BufReader<File>>,
-    end_label: &str,
-) -> (Vec<Node>, Vec<String>) {
-    let mut node_list = Vec::new();
-    let mut conn_list = Vec::new();
-
-    for line in lines {
-        let line = line.unwrap();
-        if line.is_empty() {
-            continue;
+        for node in &self.nodes {
+            if node.label.ends_with(START_LABEL2) {
+                dot += &format!(
+                    "  \"{}\" [style=filled, fillcolor=palegreen];\n",
+                    node.label
+                );
+            } else if node.label.ends_with(END_LABEL2) {
+                dot += &format!("  \"{}\" [style=filled, fillcolor=salmon];\n", node.label);
+            }
         }
 
-        let (label, connections) = line.split_once(" = ").unwrap();
-        let node = Node::new(label, end_label);
+        for node in &self.nodes {
+            let (left, right) = (&self.nodes[node.left].label, &self.nodes[node.right].label);
+            if left == right {
+                dot += &format!("  \"{}\" -> \"{left}\" [label=\"LR\"];\n", node.label);
+            } else {
+                dot += &format!("  \"{}\" -> \"{left}\" [label=\"L\"];\n", node.label);
+                dot += &format!("  \"{}\" -> \"{right}\" [label=\"R\"];\n", node.label);
+            }
+        }
 
-        node_list.push(node);
-        conn_list.push(connections.to_string());
-    }
+        for (index, path) in paths.iter().enumerate() {
+            let color = OVERLAY_COLORS[index % OVERLAY_COLORS.len()];
+            // every edge only once, even if the path uses it repeatedly
+            let mut edges: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+            for &(node, command) in path {
+                let to = self.next(node, command);
+                let command = if command == LEFT { 'L' } else { 'R' };
+                edges.entry((node, to)).or_default().insert(command);
+            }
 
-    (node_list, conn_list)
-}
+            for ((from, to), commands) in edges {
+                let label: String = commands.into_iter().collect();
+                dot += &format!(
+                    "  \"{}\" -> \"{}\" [label=\"{label}\", color={color}, penwidth=2, constraint=false];\n",
+                    self.nodes[from].label, self.nodes[to].label
+                );
+            }
+        }
 
-fn update_nodes(node_list: Vec<Node>, conn_list: Vec<String>) -> Vec<Node> {
-    conn_list
-        .iter()
-        .enumerate()
-        .map(|(i, conn)| {
-            let (left, right) = conn.split_once(", ").unwrap();
+        dot += "}\n";
+        dot
+    }
 
-            let left = left.get(1..).unwrap();
-            let left = node_list
-                .iter()
-                .position(|node| node.label == left)
-                .unwrap();
+    fn next(&self, node: usize, command: u32) -> usize {
+        match command {
+            RIGHT => self.nodes[node].right,
+            LEFT => self.nodes[node].left,
+            _ => unreachable!("there are no other commands"),
+        }
+    }
+}
 
-            let right = right.get(..right.len() - 1).unwrap();
-            let right = node_list
-                .iter()
-                .position(|node| node.label == right)
-                .unwrap();
-
-            Node::with_left_right(
-                node_list[i].label.clone(),
-                Some(left),
-                Some(right),
-                node_list[i].is_end,
-            )
+fn get_commands(line: &str) -> Result<Vec<u32>, &'static str> {
+    line.chars()
+        .map(|c| match c {
+            'R' => Ok(RIGHT),
+            'L' => Ok(LEFT),
+            _ => Err("unknown command"),
         })
         .collect()
 }
 
-fn get_steps_to_end(node_list: &[Node], command_line: &[u32], current_node: usize) -> usize {
+fn get_network(filename: &str) -> Result<(Vec<u32>, Network), &'static str> {
+    let file = match File::open(filename) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return Err("could not open file"),
+    };
+
+    let mut lines = file.lines().map_while(Result::ok);
+    let command_line = match lines.next() {
+        Some(line) => get_commands(&line)?,
+        None => return Err("no commands found"),
+    };
+    if command_line.is_empty() {
+        return Err("no commands found");
+    }
+
+    Ok((command_line, Network::new(lines)?))
+}
+
+fn get_steps_to_end(
+    network: &Network,
+    command_line: &[u32],
+    current_node: usize,
+    end_label: &str,
+) -> usize {
     let mut current_node = current_node;
     for (index, command) in command_line.iter().cycle().enumerate() {
-        match *command {
-            RIGHT => current_node = node_list[current_node].right.unwrap(),
-            LEFT => current_node = node_list[current_node].left.unwrap(),
-            _ => unreachable!("there are no other commands"),
-        }
+        current_node = network.next(current_node, *command);
 
-        if node_list[current_node].is_end {
+        if network.nodes[current_node].label.ends_with(end_label) {
             return index + 1;
         }
     }
@@ -150,51 +230,156 @@ fn get_steps_to_end(node_list: &[Node], command_line: &[u32], current_node: usiz
 }
 
 fn part1(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+    let (command_line, network) =
+        get_network(filename).expect("Should be able to parse the network");
 
-    let mut lines = file.lines();
-    let command_line = get_commands(&mut lines);
+    let current_node = network.get(START_LABEL).expect("Should have a start node");
 
-    let (node_list, conn_list) = get_nodes_conns(&mut lines, END_LABEL);
+    get_steps_to_end(&network, &command_line, current_node, END_LABEL)
+}
 
-    let node_list = update_nodes(node_list, conn_list);
+// where a ghost is on an end node, tracked by (node, command index) states
+#[derive(Debug, PartialEq, Eq)]
+struct GhostCycle {
+    // steps before the ghost enters its cycle
+    tail: usize,
+    length: usize,
+    // end steps before the cycle is entered
+    tail_ends: Vec<usize>,
+    // end steps in `tail..tail + length`, they repeat every `length` steps
+    cycle_ends: Vec<usize>,
+}
 
-    let current_node = node_list
-        .iter()
-        .position(|node| node.label.ends_with(START_LABEL))
-        .unwrap();
+impl GhostCycle {
+    fn new(network: &Network, command_line: &[u32], start: usize, end_label: &str) -> Self {
+        let mut seen = HashMap::new();
+        let mut ends = vec![];
+        let mut node = start;
+
+        for step in 0.. {
+            let state = (node, step % command_line.len());
+            if let Some(&first) = seen.get(&state) {
+                let (tail_ends, cycle_ends): (Vec<_>, _) =
+                    ends.into_iter().partition(|&end| end < first);
+                return Self {
+                    tail: first,
+                    length: step - first,
+                    // the start itself does not count, the ghost has to walk there
+                    tail_ends: tail_ends.into_iter().filter(|&end| end > 0).collect(),
+                    cycle_ends,
+                };
+            }
+            seen.insert(state, step);
 
-    get_steps_to_end(&node_list, &command_line, current_node)
+            if network.nodes[node].label.ends_with(end_label) {
+                ends.push(step);
+            }
+            node = network.next(node, command_line[state.1]);
+        }
+        unreachable!("there are only finitely many states");
+    }
+
+    fn is_end_at(&self, step: usize) -> bool {
+        if step < self.tail {
+            self.tail_ends.contains(&step)
+        } else {
+            let offset = self.tail + (step - self.tail) % self.length;
+            self.cycle_ends.contains(&offset)
+        }
+    }
 }
 
-fn part2(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+// combines `x = a1 mod m1` and `x = a2 mod m2`, the moduli don't have to be coprime
+fn crt((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let gcd = m1.extended_gcd(&m2);
+    if (a2 - a1) % gcd.gcd != 0 {
+        return None;
+    }
 
-    let mut lines = file.lines();
-    let command_line = get_commands(&mut lines);
+    let lcm = m1 / gcd.gcd * m2;
+    let k = ((a2 - a1) / gcd.gcd * gcd.x).rem_euclid(m2 / gcd.gcd);
+    Some(((a1 + m1 * k).rem_euclid(lcm), lcm))
+}
 
-    let (node_list, conn_list) = get_nodes_conns(&mut lines, END_LABEL2);
+fn earliest_sync(ghosts: &[GhostCycle]) -> Option<usize> {
+    // before every ghost is in its cycle, one of them has to be on a tail end
+    let cycle_start = ghosts.iter().map(|ghost| ghost.tail).max()?.max(1);
+    let tail_sync = ghosts
+        .iter()
+        .flat_map(|ghost| &ghost.tail_ends)
+        .filter(|&&step| step < cycle_start)
+        .filter(|&&step| ghosts.iter().all(|ghost| ghost.is_end_at(step)))
+        .min();
+    if tail_sync.is_some() {
+        return tail_sync.copied();
+    }
 
-    let node_list = update_nodes(node_list, conn_list);
+    // afterwards, every combination of cycle ends gives a system of congruences
+    let mut systems = vec![(0, 1)];
+    for ghost in ghosts {
+        systems = systems
+            .iter()
+            .flat_map(|&system| {
+                ghost
+                    .cycle_ends
+                    .iter()
+                    .filter_map(move |&end| crt(system, (end as i128, ghost.length as i128)))
+            })
+            .collect();
+    }
 
-    let current_nodes: Vec<_> = node_list
-        .iter()
-        .enumerate()
-        .filter_map(|(i, node)| {
-            if node.label.ends_with(START_LABEL2) {
-                Some(i)
+    systems
+        .into_iter()
+        .map(|(rest, modulus)| {
+            // the first step after `cycle_start` that solves the system
+            let cycle_start = cycle_start as i128;
+            if rest >= cycle_start {
+                rest
             } else {
-                None
+                rest + (cycle_start - rest + modulus - 1) / modulus * modulus
             }
         })
-        .collect();
+        .min()
+        .map(|step| step as usize)
+}
+
+// the (node, command) pairs of every ghost until it has walked its first cycle
+fn ghost_paths(network: &Network, command_line: &[u32]) -> Vec<Vec<(usize, u32)>> {
+    network
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.label.ends_with(START_LABEL2))
+        .map(|(start, _)| {
+            let ghost = GhostCycle::new(network, command_line, start, END_LABEL2);
+            let mut node = start;
+            command_line
+                .iter()
+                .cycle()
+                .take(ghost.tail + ghost.length)
+                .map(|&command| {
+                    let step = (node, command);
+                    node = network.next(node, command);
+                    step
+                })
+                .collect()
+        })
+        .collect()
+}
 
-    current_nodes
+fn part2(filename: &str) -> usize {
+    let (command_line, network) =
+        get_network(filename).expect("Should be able to parse the network");
+
+    let ghosts: Vec<_> = network
+        .nodes
         .iter()
-        .map(|n| get_steps_to_end(&node_list, &command_line, *n))
-        .fold(1, |lcm, s| integer::lcm(lcm, s))
+        .enumerate()
+        .filter(|(_, node)| node.label.ends_with(START_LABEL2))
+        .map(|(start, _)| GhostCycle::new(&network, &command_line, start, END_LABEL2))
+        .collect();
+
+    earliest_sync(&ghosts).expect("Should have a step where all ghosts are at an end")
 }
 
 #[test]
@@ -221,3 +406,109 @@ fn part2_example() {
 fn part2_puzzle() {
     assert_eq!(10371555451871, part2(PART2_FILE));
 }
+
+#[test]
+fn network_links() {
+    let lines = ["AAA = (BBB, CCC)", "CCC = (AAA, BBB)", "BBB = (BBB, BBB)"];
+    let network = Network::new(lines.into_iter().map(String::from)).unwrap();
+
+    let (aaa, bbb, ccc) = (
+        network.get("AAA").unwrap(),
+        network.get("BBB").unwrap(),
+        network.get("CCC").unwrap(),
+    );
+    assert_eq!(bbb, network.next(aaa, LEFT));
+    assert_eq!(ccc, network.next(aaa, RIGHT));
+    assert_eq!(aaa, network.next(ccc, LEFT));
+    assert_eq!(None, network.get("ZZZ"));
+
+    let parse = |lines: &[&str]| Network::new(lines.iter().map(|l| l.to_string())).map(|_| ());
+    assert_eq!(
+        Err("Node references an undefined label"),
+        parse(&["AAA = (BBB, ZZZ)", "BBB = (AAA, AAA)"])
+    );
+    assert_eq!(
+        Err("Node is defined twice"),
+        parse(&["AAA = (AAA, AAA)", "AAA = (AAA, AAA)"])
+    );
+    assert_eq!(
+        Err("no `(<left>, <right>)` found in Node"),
+        parse(&["AAA = BBB, CCC"])
+    );
+    assert_eq!(Err("unknown command"), get_commands("LRX").map(|_| ()));
+}
+
+#[test]
+fn ghost_cycles() {
+    let network = Network::new(
+        [
+            "1A = (1B, 1A)",
+            "1B = (1Z, 1B)",
+            "1Z = (1B, 1Z)",
+            "2A = (2Z, 2A)",
+            "2Z = (2C, 2Z)",
+            "2C = (2D, 2C)",
+            "2D = (2Z, 2D)",
+            "3A = (3Z, 3A)",
+            "3Z = (3B, 3Z)",
+            "3B = (3Z, 3B)",
+            "4A = (4Z, 4A)",
+            "4Z = (4B, 4Z)",
+            "4B = (4B, 4B)",
+        ]
+        .into_iter()
+        .map(String::from),
+    )
+    .unwrap();
+    let command_line = get_commands("L").unwrap();
+    let ghost = |start| GhostCycle::new(&network, &command_line, network.get(start).unwrap(), "Z");
+
+    let ghosts = ["1A", "2A", "3A", "4A"].map(ghost);
+    assert_eq!(
+        GhostCycle {
+            tail: 1,
+            length: 3,
+            tail_ends: vec![],
+            cycle_ends: vec![1],
+        },
+        ghosts[1]
+    );
+    assert_eq!(vec![1], ghosts[3].tail_ends);
+
+    // the lcm of the first arrivals would be 2, where ghost 2 is on `2C`
+    assert_eq!(Some(4), earliest_sync(&[ghost("1A"), ghost("2A")]));
+    assert_eq!(Some(1), earliest_sync(&[ghost("2A"), ghost("4A")]));
+    assert_eq!(None, earliest_sync(&[ghost("1A"), ghost("3A")]));
+    assert_eq!(None, earliest_sync(&[ghost("1A"), ghost("4A")]));
+    assert_eq!(Some(3), earliest_sync(&[ghost("2Z")]));
+
+    for (ghosts, expected) in [(["1A", "2A", "3A"], None), (["2A", "3A", "2A"], Some(1))] {
+        let ghosts = ghosts.map(ghost);
+        let brute_force = (1..1000).find(|&step| ghosts.iter().all(|g| g.is_end_at(step)));
+        assert_eq!(expected, brute_force);
+        assert_eq!(expected, earliest_sync(&ghosts));
+    }
+}
+
+#[test]
+fn network_dot() {
+    let (command_line, network) = get_network("test2.txt").unwrap();
+    let paths = ghost_paths(&network, &command_line);
+    assert_eq!(2, paths.len());
+    assert_eq!(3, paths[0].len());
+
+    let dot = network.to_dot(&paths);
+    assert!(dot.starts_with("digraph network {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("  \"11A\" [style=filled, fillcolor=palegreen];\n"));
+    assert!(dot.contains("  \"22Z\" [style=filled, fillcolor=salmon];\n"));
+    assert!(dot.contains("  \"11A\" -> \"11B\" [label=\"L\"];\n"));
+    assert!(dot.contains("  \"11A\" -> \"XXX\" [label=\"R\"];\n"));
+    assert!(dot.contains("  \"XXX\" -> \"XXX\" [label=\"LR\"];\n"));
+    assert!(dot.contains(
+        "  \"11B\" -> \"11Z\" [label=\"R\", color=red, penwidth=2, constraint=false];\n"
+    ));
+    assert!(dot.contains(
+        "  \"22B\" -> \"22C\" [label=\"LR\", color=blue, penwidth=2, constraint=false];\n"
+    ));
+}
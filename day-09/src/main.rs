@@ -1,12 +1,18 @@
+use num::rational::Ratio;
+use num::CheckedAdd;
+use std::collections::BTreeMap;
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fmt;
+use std::fs;
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
+const RUNS_OUT: &str = "the differences run out before reaching all zeros";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-09 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-09 p<n> \
+                 | extrapolate <file> <steps> [skip|reject [<max degree>]] \
+                 | report <file> <steps> [<max degree>] | fit <file>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -17,6 +23,78 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "extrapolate" | "report" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let (file, steps, policy, max_degree) = match (part.as_str(), args.as_slice()) {
+                    (_, [file, steps]) => (file, steps, None, None),
+                    ("extrapolate", [file, steps, policy]) => (file, steps, Some(policy), None),
+                    ("extrapolate", [file, steps, policy, max]) => {
+                        (file, steps, Some(policy), Some(max))
+                    }
+                    ("report", [file, steps, max]) => (file, steps, None, Some(max)),
+                    _ => {
+                        eprintln!("{usage}");
+                        return;
+                    }
+                };
+                let max_degree = match max_degree.map(|max| max.parse()).transpose() {
+                    Ok(max_degree) => max_degree,
+                    Err(_) => {
+                        eprintln!("An error occurred:\ncouldn't parse max degree");
+                        return;
+                    }
+                };
+                let steps = match steps.parse() {
+                    Ok(steps) => steps,
+                    Err(_) => {
+                        eprintln!("An error occurred:\ncouldn't parse steps");
+                        return;
+                    }
+                };
+                let histories = match read_histories(file) {
+                    Ok(histories) => histories,
+                    Err(e) => {
+                        eprintln!("An error occurred:\n{e}");
+                        return;
+                    }
+                };
+
+                if part == "report" {
+                    let diagnostics = diagnose(&histories, steps, max_degree);
+                    diagnostics.iter().for_each(|diag| println!("{diag}"));
+                    println!("{}", Summary::new(&diagnostics));
+                    return;
+                }
+
+                let sum = policy
+                    .map_or(Ok(Invalid::Reject), |policy| Invalid::new(policy))
+                    .and_then(|invalid| solve(&histories, steps, max_degree, invalid));
+                match sum {
+                    Ok(sum) => println!("Sum is {sum}"),
+                    Err(e) => eprintln!("An error occurred:\n{e}"),
+                }
+            }
+            "fit" => match env::args().nth(2).map(|f| read_histories(&f)) {
+                Some(Ok(histories)) => {
+                    for history in histories {
+                        let fitted = Polynomial::fit(&history)
+                            .and_then(|poly| Ok((poly.degree(), poly.coefficients()?)));
+                        match fitted {
+                            Ok((degree, coeffs)) => println!(
+                                "degree {degree}: {}",
+                                coeffs
+                                    .iter()
+                                    .map(|coeff| coeff.to_string())
+                                    .collect::<Vec<_>>()
+                                    .join(" ")
+                            ),
+                            Err(e) => println!("{e}"),
+                        }
+                    }
+                }
+                Some(Err(e)) => eprintln!("An error occurred:\n{e}"),
+                None => eprintln!("{usage}"),
+            },
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -24,50 +102,315 @@ fn main() {
     }
 }
 
-fn add_last(line: &str) -> i64 {
-    let mut last_nums = Vec::new();
-    let mut nums: Vec<i64> = line
-        .split_ascii_whitespace()
-        .map(|n| n.parse().unwrap())
-        .collect();
+fn parse_history(line: &str) -> Result<Vec<i64>, &'static str> {
+    line.split_ascii_whitespace()
+        .map(|n| match n.parse() {
+            Ok(n) => Ok(n),
+            Err(_) => Err("couldn't parse number in history"),
+        })
+        .collect()
+}
+
+fn read_histories(filename: &str) -> Result<Vec<Vec<i64>>, &'static str> {
+    let input = match fs::read_to_string(filename) {
+        Ok(input) => input,
+        Err(_) => return Err("could not read file"),
+    };
+
+    input.lines().map(parse_history).collect()
+}
+
+// the history as a polynomial in x, where the history holds the values at x = 0, 1, 2, ...
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Polynomial {
+    // newton forward differences, so the value at x is the sum of `diffs[j] * binomial(x, j)`
+    diffs: Vec<i128>,
+    len: usize,
+}
+
+impl Polynomial {
+    fn fit(history: &[i64]) -> Result<Self, &'static str> {
+        // a single value shows no differences, so not even a constant is confirmed
+        if history.len() < 2 {
+            return Err(RUNS_OUT);
+        }
+
+        let mut diffs = vec![];
+        let mut nums = history.iter().map(|&n| n as i128).collect::<Vec<_>>();
+
+        while !nums.iter().all(|&n| n == 0) {
+            if nums.len() == 1 {
+                return Err(RUNS_OUT);
+            }
+            diffs.push(nums[0]);
+            nums = nums
+                .windows(2)
+                .map(|w| match w[1].checked_sub(w[0]) {
+                    Some(diff) => Ok(diff),
+                    None => Err("difference overflows i128"),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        Ok(Self {
+            diffs,
+            len: history.len(),
+        })
+    }
+
+    // the zero polynomial also counts as degree 0
+    fn degree(&self) -> usize {
+        self.diffs.len().saturating_sub(1)
+    }
+
+    fn at(&self, x: i64) -> Result<i64, &'static str> {
+        let overflow = "extrapolated value overflows i64";
+        let x = x as i128;
+        let mut value: i128 = 0;
+        // binomial(x, j) stays an integer for negative x too, and each step divides exactly
+        let mut binomial: i128 = 1;
+
+        for (j, diff) in self.diffs.iter().enumerate() {
+            if j > 0 {
+                binomial = match binomial.checked_mul(x - j as i128 + 1) {
+                    Some(product) => product / j as i128,
+                    None => return Err(overflow),
+                };
+            }
+            value = match diff
+                .checked_mul(binomial)
+                .and_then(|term| value.checked_add(term))
+            {
+                Some(value) => value,
+                None => return Err(overflow),
+            };
+        }
+
+        match i64::try_from(value) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(overflow),
+        }
+    }
 
-    while !nums.iter().all(|n| *n == 0) {
-        last_nums.push(*nums.last().unwrap());
-        nums = nums.windows(2).map(|w| w[1] - w[0]).collect();
+    // positive steps count on from the last value, negative ones back from the first
+    fn extrapolate(&self, steps: i64) -> Result<i64, &'static str> {
+        if steps >= 0 {
+            match (self.len as i64 - 1).checked_add(steps) {
+                Some(x) => self.at(x),
+                None => Err("extrapolated value overflows i64"),
+            }
+        } else {
+            self.at(steps)
+        }
     }
 
-    last_nums.iter().sum()
+    // coefficients of x^0, x^1, ..., x^degree; empty for the zero polynomial
+    fn coefficients(&self) -> Result<Vec<Ratio<i64>>, &'static str> {
+        let overflow = "coefficient overflows i64";
+        let mut coeffs = vec![Ratio::from_integer(0i128); self.diffs.len()];
+        // x (x - 1) ... (x - j + 1) expanded, and j!
+        let mut falling = vec![1i128];
+        let mut factorial: i128 = 1;
+
+        for (j, diff) in self.diffs.iter().enumerate() {
+            if j > 0 {
+                let mut next = vec![0; falling.len() + 1];
+                for (k, &coeff) in falling.iter().enumerate() {
+                    next[k + 1] += coeff;
+                    next[k] = match (j as i128 - 1)
+                        .checked_mul(coeff)
+                        .and_then(|product| next[k].checked_sub(product))
+                    {
+                        Some(coeff) => coeff,
+                        None => return Err(overflow),
+                    };
+                }
+                falling = next;
+                factorial = match factorial.checked_mul(j as i128) {
+                    Some(factorial) => factorial,
+                    None => return Err(overflow),
+                };
+            }
+
+            for (k, &coeff) in falling.iter().enumerate() {
+                coeffs[k] = match diff
+                    .checked_mul(coeff)
+                    .and_then(|numer| coeffs[k].checked_add(&Ratio::new(numer, factorial)))
+                {
+                    Some(coeff) => coeff,
+                    None => return Err(overflow),
+                };
+            }
+        }
+
+        coeffs
+            .into_iter()
+            .map(
+                |coeff| match (i64::try_from(*coeff.numer()), i64::try_from(*coeff.denom())) {
+                    (Ok(numer), Ok(denom)) => Ok(Ratio::new(numer, denom)),
+                    _ => Err(overflow),
+                },
+            )
+            .collect()
+    }
 }
 
-fn part1(filename: &str) -> i64 {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+fn extrapolate(history: &[i64], steps: i64) -> Result<i64, &'static str> {
+    Polynomial::fit(history)?.extrapolate(steps)
+}
 
-    file.lines()
-        .fold(0, |sum, line| sum + add_last(&line.unwrap()))
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Classification {
+    Polynomial(usize),
+    InsufficientData,
+    NonPolynomial,
 }
 
-fn add_first(line: &str) -> i64 {
-    let mut first_nums = Vec::new();
-    let mut nums: Vec<i64> = line
-        .split_ascii_whitespace()
-        .map(|n| n.parse().unwrap())
-        .collect();
+impl Classification {
+    // confirming degree d takes at least d + 2 values, so a history whose differences run
+    // out could still be a polynomial of degree `len - 1` or more. it is only ruled out
+    // when that is above `max_degree`, the largest degree the caller is willing to fit
+    fn new(history: &[i64], max_degree: Option<usize>) -> Self {
+        match Polynomial::fit(history) {
+            Ok(poly) => Self::Polynomial(poly.degree()),
+            Err(_) if max_degree.is_some_and(|max| history.len() > max + 1) => Self::NonPolynomial,
+            Err(_) => Self::InsufficientData,
+        }
+    }
+}
 
-    while !nums.iter().all(|n| *n == 0) {
-        first_nums.push(*nums.first().unwrap());
-        nums = nums.windows(2).map(|w| w[1] - w[0]).collect();
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Polynomial(degree) => write!(f, "polynomial of degree {degree}"),
+            Self::InsufficientData => write!(f, "insufficient data"),
+            Self::NonPolynomial => write!(f, "non-polynomial"),
+        }
     }
+}
 
-    first_nums.iter().rev().fold(0, |diff, num| num - diff)
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Diagnostic {
+    // 1-based, like the line numbers of an editor
+    line: usize,
+    classification: Classification,
+    value: Result<i64, &'static str>,
 }
 
-fn part2(filename: &str) -> i64 {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.classification)?;
+        match self.value {
+            Ok(value) => write!(f, ", extrapolated {value}"),
+            Err(e) => write!(f, ", {e}"),
+        }
+    }
+}
+
+fn diagnose(histories: &[Vec<i64>], steps: i64, max_degree: Option<usize>) -> Vec<Diagnostic> {
+    histories
+        .iter()
+        .enumerate()
+        .map(|(index, history)| Diagnostic {
+            line: index + 1,
+            classification: Classification::new(history, max_degree),
+            value: extrapolate(history, steps),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Summary {
+    // degree to number of lines
+    degrees: BTreeMap<usize, usize>,
+    insufficient: usize,
+    non_polynomial: usize,
+}
+
+impl Summary {
+    fn new(diagnostics: &[Diagnostic]) -> Self {
+        let mut summary = Self::default();
+        for diag in diagnostics {
+            match diag.classification {
+                Classification::Polynomial(degree) => {
+                    *summary.degrees.entry(degree).or_default() += 1
+                }
+                Classification::InsufficientData => summary.insufficient += 1,
+                Classification::NonPolynomial => summary.non_polynomial += 1,
+            }
+        }
+        summary
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let polynomial = self.degrees.values().sum::<usize>();
+        write!(f, "{polynomial} polynomial")?;
+        if !self.degrees.is_empty() {
+            let degrees = self
+                .degrees
+                .iter()
+                .map(|(degree, count)| format!("degree {degree}: {count}"))
+                .collect::<Vec<_>>();
+            write!(f, " ({})", degrees.join(", "))?;
+        }
+        write!(
+            f,
+            ", {} insufficient data, {} non-polynomial",
+            self.insufficient, self.non_polynomial
+        )
+    }
+}
+
+// what the solver does with lines that aren't polynomial sequences
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Invalid {
+    Skip,
+    Reject,
+}
+
+impl Invalid {
+    fn new(name: &str) -> Result<Self, &'static str> {
+        match name {
+            "skip" => Ok(Self::Skip),
+            "reject" => Ok(Self::Reject),
+            _ => Err("unknown policy for invalid lines"),
+        }
+    }
+}
+
+fn solve(
+    histories: &[Vec<i64>],
+    steps: i64,
+    max_degree: Option<usize>,
+    invalid: Invalid,
+) -> Result<i64, &'static str> {
+    let mut sum: i64 = 0;
 
-    file.lines()
-        .fold(0, |sum, line| sum + add_first(&line.unwrap()))
+    for history in histories {
+        let value = match (Classification::new(history, max_degree), invalid) {
+            (Classification::Polynomial(_), _) => extrapolate(history, steps)?,
+            (_, Invalid::Skip) => continue,
+            (_, Invalid::Reject) => return Err("history is not a polynomial sequence"),
+        };
+        sum = match sum.checked_add(value) {
+            Some(sum) => sum,
+            None => return Err("sum overflows i64"),
+        };
+    }
+
+    Ok(sum)
+}
+
+fn part1(filename: &str) -> i64 {
+    let histories = read_histories(filename).expect("Should be able to parse the histories");
+    solve(&histories, 1, None, Invalid::Reject).expect("Should be able to extrapolate")
+}
+
+fn part2(filename: &str) -> i64 {
+    let histories = read_histories(filename).expect("Should be able to parse the histories");
+    solve(&histories, -1, None, Invalid::Reject).expect("Should be able to extrapolate")
 }
 
 #[test]
@@ -89,3 +432,107 @@ fn part2_example() {
 fn part2_puzzle() {
     assert_eq!(1005, part2(PART2_FILE));
 }
+
+#[test]
+fn polynomial_fit() {
+    let linear = Polynomial::fit(&[0, 3, 6, 9, 12, 15]).unwrap();
+    assert_eq!(1, linear.degree());
+    assert_eq!(Ok(24), linear.extrapolate(3));
+    assert_eq!(Ok(-6), linear.extrapolate(-2));
+    assert_eq!(
+        Ok(vec![Ratio::from(0), Ratio::from(3)]),
+        linear.coefficients()
+    );
+
+    let triangular = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+    assert_eq!(2, triangular.degree());
+    assert_eq!(Ok(28), triangular.extrapolate(1));
+    assert_eq!(Ok(45), triangular.extrapolate(3));
+    assert_eq!(Ok(0), triangular.extrapolate(-1));
+    assert_eq!(Ok(0), triangular.extrapolate(-2));
+    assert_eq!(Ok(1), triangular.extrapolate(-3));
+    assert_eq!(
+        Ok(vec![Ratio::from(1), Ratio::new(3, 2), Ratio::new(1, 2)]),
+        triangular.coefficients()
+    );
+
+    let zero = Polynomial::fit(&[0, 0]).unwrap();
+    assert_eq!(Ok(0), zero.extrapolate(100));
+    assert_eq!(Ok(vec![]), zero.coefficients());
+}
+
+#[test]
+fn polynomial_invalid() {
+    assert_eq!(Err(RUNS_OUT), Polynomial::fit(&[1, 2, 4, 8]));
+    assert_eq!(Err(RUNS_OUT), Polynomial::fit(&[1, 2]));
+    assert_eq!(Err(RUNS_OUT), Polynomial::fit(&[7]));
+    assert_eq!(
+        Err("extrapolated value overflows i64"),
+        extrapolate(&[0, 1 << 60, 1 << 61], 10)
+    );
+    assert_eq!(
+        Err("extrapolated value overflows i64"),
+        extrapolate(&[0, 1, 2], i64::MAX)
+    );
+}
+
+#[test]
+fn classify_histories() {
+    let histories = vec![
+        vec![0, 3, 6, 9, 12, 15],
+        vec![1, 2, 4, 8],
+        vec![5],
+        vec![1, 3, 6, 10, 15, 21],
+        vec![],
+        vec![2, 2, 2],
+        vec![1, 2],
+    ];
+    let classify = |max_degree| {
+        diagnose(&histories, 1, max_degree)
+            .iter()
+            .map(|diag| diag.classification)
+            .collect::<Vec<_>>()
+    };
+
+    // without a largest degree, running out of differences only means too few values
+    assert_eq!(
+        vec![
+            Classification::Polynomial(1),
+            Classification::InsufficientData,
+            Classification::InsufficientData,
+            Classification::Polynomial(2),
+            Classification::InsufficientData,
+            Classification::Polynomial(0),
+            Classification::InsufficientData,
+        ],
+        classify(None)
+    );
+    // 4 values that don't settle by degree 2 can't be a quadratic, but 2 could still be
+    assert_eq!(Classification::NonPolynomial, classify(Some(2))[1]);
+    assert_eq!(Classification::InsufficientData, classify(Some(2))[6]);
+    assert_eq!(Classification::NonPolynomial, classify(Some(0))[6]);
+
+    let diagnostics = diagnose(&histories, 1, Some(2));
+    assert_eq!(
+        "line 1: polynomial of degree 1, extrapolated 18",
+        diagnostics[0].to_string()
+    );
+    assert_eq!(
+        "line 2: non-polynomial, the differences run out before reaching all zeros",
+        diagnostics[1].to_string()
+    );
+    assert_eq!(
+        "line 7: insufficient data, the differences run out before reaching all zeros",
+        diagnostics[6].to_string()
+    );
+    assert_eq!(
+        "3 polynomial (degree 0: 1, degree 1: 1, degree 2: 1), 3 insufficient data, 1 non-polynomial",
+        Summary::new(&diagnostics).to_string()
+    );
+
+    assert_eq!(Ok(18 + 28 + 2), solve(&histories, 1, None, Invalid::Skip));
+    assert_eq!(
+        Err("history is not a polynomial sequence"),
+        solve(&histories, 1, Some(2), Invalid::Reject)
+    );
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, env};
+use std::{collections::HashSet, env, fs};
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
@@ -6,9 +6,14 @@ const WEST: Point = Point { row: 0, col: -1 };
 const EAST: Point = Point { row: 0, col: 1 };
 const NORTH: Point = Point { row: -1, col: 0 };
 const SOUTH: Point = Point { row: 1, col: 0 };
+const DIM: &str = "\x1b[2m";
+const BOLD: &str = "\x1b[1m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-10 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-10 p<n> | trace <file> \
+                 | render <file> [plain|color] [<output>]";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -19,6 +24,46 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "trace" => match env::args().nth(2).map(|f| read_loop(&f)) {
+                Some(Ok(pipe_loop)) => {
+                    println!("Loop of length {}", pipe_loop.length());
+                    for point in &pipe_loop.points {
+                        println!("({}, {})", point.row, point.col);
+                    }
+                }
+                Some(Err(e)) => eprintln!("An error occurred:\n{e}"),
+                None => eprintln!("{usage}"),
+            },
+            "render" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let (file, style, output) = match args.as_slice() {
+                    [file] => (file, "plain", None),
+                    [file, style] => (file, style.as_str(), None),
+                    [file, style, output] => (file, style.as_str(), Some(output)),
+                    _ => {
+                        eprintln!("{usage}");
+                        return;
+                    }
+                };
+                let rendered = Style::new(style).and_then(|style| {
+                    let map = read_map(file)?;
+                    let pipe_loop = map.trace()?;
+                    Ok(map.render(&pipe_loop, style))
+                });
+                let written = rendered.and_then(|rendered| match output {
+                    Some(output) => match fs::write(output, rendered) {
+                        Ok(()) => Ok(()),
+                        Err(_) => Err("could not write file"),
+                    },
+                    None => {
+                        print!("{rendered}");
+                        Ok(())
+                    }
+                });
+                if let Err(e) = written {
+                    eprintln!("An error occurred:\n{e}");
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -64,197 +109,254 @@ fn get_pipe_deltas(pipe: char) -> (Point, Point) {
     }
 }
 
-fn is_pipe(pipe: char) -> bool {
-    match pipe {
-        '|' | 'L' | 'J' | '7' | 'F' | 'S' | '-' => true,
-        _ => false,
+fn get_pipe_from_deltas(d1: Point, d2: Point) -> Option<char> {
+    ['|', '-', 'L', 'J', '7', 'F'].into_iter().find(|&pipe| {
+        let deltas = get_pipe_deltas(pipe);
+        deltas == (d1, d2) || deltas == (d2, d1)
+    })
+}
+
+fn get_box_char(tile: char) -> char {
+    match tile {
+        '|' => '│',
+        '-' => '─',
+        'L' => '└',
+        'J' => '┘',
+        '7' => '┐',
+        'F' => '┌',
+        _ => '·',
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Tile {
+    Loop,
+    Inside,
+    Outside,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Style {
+    Plain,
+    Color,
+}
+
+impl Style {
+    fn new(name: &str) -> Result<Self, &'static str> {
+        match name {
+            "plain" => Ok(Self::Plain),
+            "color" => Ok(Self::Color),
+            _ => Err("unknown render style"),
+        }
     }
 }
 
 struct PipeMap {
+    // `S` is replaced by the pipe it hides
     points: Vec<Vec<char>>,
-    trace_a: Vec<Point>,
-    trace_b: Vec<Point>,
-    point_set: HashSet<Point>,
-    step_count: usize,
+    start: Point,
 }
 
 impl PipeMap {
-    fn new(file: &str) -> Self {
-        println!("{file}");
-        let points: Vec<Vec<_>> = file
-            .split_ascii_whitespace()
-            .map(|part| part.chars().collect())
-            .collect();
-
-        let start = points.iter().flatten().position(|c| *c == 'S').unwrap();
-        let row = start / points.first().unwrap().len();
-        let col = start % points.first().unwrap().len();
-        let start = Point::new(row, col);
-
-        let mut point_set = HashSet::new();
-        point_set.insert(start);
+    fn new(file: &str) -> Result<Self, &'static str> {
+        let points: Vec<Vec<_>> = file.lines().map(|line| line.chars().collect()).collect();
+
+        let width = match points.first() {
+            Some(row) if !row.is_empty() => row.len(),
+            _ => return Err("empty pipe map"),
+        };
+        if points.iter().any(|row| row.len() != width) {
+            return Err("pipe map is not rectangular");
+        }
+        if points
+            .iter()
+            .flatten()
+            .any(|c| !matches!(c, '|' | '-' | 'L' | 'J' | '7' | 'F' | '.' | 'S'))
+        {
+            return Err("unknown tile in pipe map");
+        }
 
-        let trace_a = vec![start];
-        let trace_b = vec![start];
+        let mut starts = points.iter().enumerate().flat_map(|(row, line)| {
+            line.iter()
+                .enumerate()
+                .filter(|(_, c)| **c == 'S')
+                .map(move |(col, _)| Point::new(row, col))
+        });
+        let start = match (starts.next(), starts.next()) {
+            (Some(start), None) => start,
+            (None, _) => return Err("no `S` found in pipe map"),
+            (Some(_), Some(_)) => return Err("more than one `S` found in pipe map"),
+        };
+
+        let mut map = Self { points, start };
+
+        // `S` hides the pipe that joins its two connected neighbours
+        let deltas: Vec<Point> = [NORTH, SOUTH, EAST, WEST]
+            .into_iter()
+            .filter(|delta| map.connects_to(start + *delta, start))
+            .collect();
+        let start_pipe = match deltas.as_slice() {
+            &[d1, d2] => get_pipe_from_deltas(d1, d2).unwrap(),
+            &[] | &[_] => return Err("dead end: `S` connects to fewer than two pipes"),
+            _ => return Err("ambiguous start: `S` connects to more than two pipes"),
+        };
+        map.points[start.row as usize][start.col as usize] = start_pipe;
+
+        Ok(map)
+    }
 
-        Self {
-            points,
-            trace_a,
-            trace_b,
-            point_set,
-            step_count: 0,
+    fn get_pipe(&self, point: &Point) -> Option<char> {
+        if point.row < 0 || point.col < 0 {
+            return None;
+        }
+        let sym = *self
+            .points
+            .get(point.row as usize)?
+            .get(point.col as usize)?;
+        match sym {
+            '|' | '-' | 'L' | 'J' | '7' | 'F' => Some(sym),
+            _ => None,
         }
     }
 
-    fn points_match(&self) -> bool {
-        let curr_a = self.trace_a.last().unwrap();
-        let curr_b = self.trace_b.last().unwrap();
-        if curr_a == curr_b {
-            return true;
+    fn connects_to(&self, point: Point, other: Point) -> bool {
+        match self.get_pipe(&point) {
+            Some(pipe) => {
+                let (d1, d2) = get_pipe_deltas(pipe);
+                point + d1 == other || point + d2 == other
+            }
+            None => false,
         }
-
-        let prev_a = self.trace_a.get(self.trace_a.len() - 2).unwrap();
-        let prev_b = self.trace_b.get(self.trace_b.len() - 2).unwrap();
-
-        curr_a == prev_b && curr_b == prev_a
     }
 
-    fn get_next_point(&self, trace: &[Point]) -> Point {
-        let curr_p = trace.last().unwrap();
-        let curr_sym = self.get_pipe(curr_p).unwrap();
-        let (d1, d2) = get_pipe_deltas(curr_sym);
+    fn trace(&self) -> Result<PipeLoop, &'static str> {
+        let mut points = vec![self.start];
+        let (delta, _) = get_pipe_deltas(self.get_pipe(&self.start).unwrap());
+        let mut prev = self.start;
+        let mut curr = self.start + delta;
 
-        if *curr_p + d1 != *trace.get(trace.len() - 2).unwrap() {
-            *curr_p + d1
-        } else {
-            *curr_p + d2
+        while curr != self.start {
+            if !self.connects_to(curr, prev) {
+                return Err("dead end: the loop is broken");
+            }
+            let (d1, d2) = get_pipe_deltas(self.get_pipe(&curr).unwrap());
+            let next = if curr + d1 != prev {
+                curr + d1
+            } else {
+                curr + d2
+            };
+
+            points.push(curr);
+            (prev, curr) = (curr, next);
         }
-    }
 
-    fn advance_points(&mut self) {
-        self.step_count += 1;
+        Ok(PipeLoop { points })
+    }
 
-        let new_a = self.get_next_point(&self.trace_a);
-        self.point_set.insert(new_a);
-        self.trace_a.push(new_a);
+    // a tile is inside when a scan from the left edge crosses the loop an odd number of
+    // times, counting only pipes that reach north so that runs along the loop cross once
+    fn classify(&self, pipe_loop: &PipeLoop) -> Vec<Vec<Tile>> {
+        let on_loop: HashSet<_> = pipe_loop.points.iter().collect();
 
-        let new_b = self.get_next_point(&self.trace_b);
-        self.point_set.insert(new_b);
-        self.trace_b.push(new_b);
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(row, line)| {
+                let mut inside = false;
+                line.iter()
+                    .enumerate()
+                    .map(|(col, tile)| {
+                        if !on_loop.contains(&Point::new(row, col)) {
+                            if inside {
+                                Tile::Inside
+                            } else {
+                                Tile::Outside
+                            }
+                        } else {
+                            if matches!(tile, '|' | 'L' | 'J') {
+                                inside = !inside;
+                            }
+                            Tile::Loop
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
-    fn get_pipe(&self, point: &Point) -> Option<char> {
-        if point.row < 0 || point.col < 0 {
-            None
-        } else {
-            let sym = self.points[point.row as usize][point.col as usize];
-            match sym {
-                '|' | '-' | 'L' | 'J' | '7' | 'F' => Some(sym),
-                _ => None,
+    // plain output marks enclosed tiles `I` and outside ones `O`, colored output keeps
+    // every tile but dims those off the loop and greens the enclosed ones
+    fn render(&self, pipe_loop: &PipeLoop, style: Style) -> String {
+        let mut rendered = String::new();
+
+        for (line, tiles) in self.points.iter().zip(self.classify(pipe_loop)) {
+            for (&sym, tile) in line.iter().zip(tiles) {
+                let sym = get_box_char(sym);
+                match (style, tile) {
+                    (Style::Plain, Tile::Loop) => rendered.push(sym),
+                    (Style::Plain, Tile::Inside) => rendered.push('I'),
+                    (Style::Plain, Tile::Outside) => rendered.push('O'),
+                    (Style::Color, Tile::Loop) => rendered.push_str(&format!("{BOLD}{sym}{RESET}")),
+                    (Style::Color, Tile::Inside) => {
+                        rendered.push_str(&format!("{DIM}{GREEN}{sym}{RESET}"))
+                    }
+                    (Style::Color, Tile::Outside) => {
+                        rendered.push_str(&format!("{DIM}{sym}{RESET}"))
+                    }
+                }
             }
+            rendered.push('\n');
         }
+
+        rendered
     }
+}
 
-    fn advance_from_start(&mut self) {
-        self.step_count += 1;
+struct PipeLoop {
+    // in walking order, starting from `S`
+    points: Vec<Point>,
+}
 
-        let start = *self.trace_a.last().unwrap();
+impl PipeLoop {
+    fn length(&self) -> usize {
+        self.points.len()
+    }
 
-        let next_points: Vec<Point> = [NORTH, SOUTH, EAST, WEST]
+    // the shoelace formula gives the area, then pick's theorem the tiles strictly inside
+    fn count_points_in_loop(&self) -> usize {
+        let double_area = self
+            .points
             .iter()
-            .filter_map(|delta| {
-                let next_p = start + *delta;
-                if let Some(next_sym) = self.get_pipe(&next_p) {
-                    let (d1, d2) = get_pipe_deltas(next_sym);
-                    (next_p + d1 == start || next_p + d2 == start).then_some(next_p)
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        let (new_a, new_b) = (next_points[0], next_points[1]);
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|(p, q)| p.row * q.col - q.row * p.col)
+            .sum::<isize>()
+            .unsigned_abs();
 
-        self.point_set.insert(new_a);
-        self.trace_a.push(new_a);
-
-        self.point_set.insert(new_b);
-        self.trace_b.push(new_b);
+        (double_area + 2 - self.length()) / 2
     }
+}
 
-    fn count_points_in_loop(&self) -> usize {
-        self.points.iter().enumerate().fold(0, |sum, (row, vec)| {
-            let s = vec[1..vec.len() - 1]
-                .iter()
-                .enumerate()
-                .scan(
-                    (false, 0),
-                    |(last_was_vertical, crossed_pipes), (col, character)| {
-                        if last_was_vertical {
-                            print!("|");
-                            *crossed_pipes += 1;
-                            Some(0)
-                        } else {
-                            match character {
-                                '-' => { /* don't increment */ }
-                                _ => {
-                                    print!("|");
-                                    *crossed_pipes += 1;
-                                    Some(0)
-                                }
-                            }
-                        }
-                        let p = Point::new(row, col);
-                        if self.point_set.contains(&p) {
-                            // if is_vertical_pipe(*character) {
-                            print!("|");
-                            *crossed_pipes += 1;
-                            Some(0)
-                            // } else {
-                            //     print!("-");
-                            //     Some(0)
-                            // }
-                        } else if *crossed_pipes % 2 != 0 {
-                            print!("+");
-                            Some(1)
-                        } else {
-                            print!(".");
-                            Some(0)
-                        }
-                    },
-                )
-                .sum::<usize>();
-            print!("\n");
-            sum + s
-        })
+fn read_map(filename: &str) -> Result<PipeMap, &'static str> {
+    match fs::read_to_string(filename) {
+        Ok(file) => PipeMap::new(&file),
+        Err(_) => Err("could not read file"),
     }
 }
 
-fn part1(filename: &str) -> usize {
-    let file = std::fs::read_to_string(filename).unwrap();
-
-    let mut char_array = PipeMap::new(&file);
-    char_array.advance_from_start();
+fn read_loop(filename: &str) -> Result<PipeLoop, &'static str> {
+    read_map(filename)?.trace()
+}
 
-    while !char_array.points_match() {
-        char_array.advance_points();
-    }
+fn part1(filename: &str) -> usize {
+    let pipe_loop = read_loop(filename).expect("Should be able to trace the loop");
 
-    char_array.step_count
+    pipe_loop.length() / 2
 }
 
 fn part2(filename: &str) -> usize {
-    let file = std::fs::read_to_string(filename).unwrap();
-
-    let mut char_array = PipeMap::new(&file);
-    char_array.advance_from_start();
-
-    while !char_array.points_match() {
-        char_array.advance_points();
-    }
+    let pipe_loop = read_loop(filename).expect("Should be able to trace the loop");
 
-    char_array.count_points_in_loop()
+    pipe_loop.count_points_in_loop()
 }
 
 #[test]
@@ -282,7 +384,105 @@ fn part2_example1() {
     assert_eq!(8, part2("test4.txt"));
 }
 
-// #[test]
-// fn part2_puzzle() {
-//     assert_eq!(250506580, part2(PART2_FILE));
-// }
+#[test]
+fn part2_puzzle() {
+    assert_eq!(443, part2(PART2_FILE));
+}
+
+#[test]
+fn start_pipe() {
+    for (filename, pipe) in [("test1.txt", 'F'), ("test2.txt", 'F'), ("test4.txt", 'F')] {
+        let map = PipeMap::new(&fs::read_to_string(filename).unwrap()).unwrap();
+        assert_eq!(
+            Some(pipe),
+            map.get_pipe(&map.start),
+            "start pipe of {filename}"
+        );
+    }
+
+    for (file, pipe) in [
+        ("S7\nLJ", 'F'),
+        ("F-7\n|.|\nL-S", 'J'),
+        ("F-S\n|.|\nL-J", '7'),
+        ("F-7\n|.|\nS-J", 'L'),
+        ("F-7\nS.|\nL-J", '|'),
+        ("FS7\n|.|\nL-J", '-'),
+    ] {
+        let map = PipeMap::new(file).unwrap();
+        assert_eq!(
+            Some(pipe),
+            map.get_pipe(&map.start),
+            "start pipe of {file:?}"
+        );
+        assert!(map.trace().is_ok(), "loop of {file:?}");
+    }
+}
+
+#[test]
+fn trace_loop() {
+    let pipe_loop = PipeMap::new("S7\nLJ").unwrap().trace().unwrap();
+    assert_eq!(
+        vec![
+            Point::new(0, 0),
+            Point::new(1, 0),
+            Point::new(1, 1),
+            Point::new(0, 1)
+        ],
+        pipe_loop.points
+    );
+    assert_eq!(4, pipe_loop.length());
+    assert_eq!(0, pipe_loop.count_points_in_loop());
+
+    // `S` in the bottom right corner
+    let pipe_loop = PipeMap::new("F-7\n|.|\nL-S").unwrap().trace().unwrap();
+    assert_eq!(8, pipe_loop.length());
+    assert_eq!(1, pipe_loop.count_points_in_loop());
+}
+
+#[test]
+fn trace_invalid() {
+    let trace = |file| PipeMap::new(file).and_then(|map| map.trace()).err();
+
+    assert_eq!(Some("pipe map is not rectangular"), trace("S7\nL"));
+    assert_eq!(Some("unknown tile in pipe map"), trace("S7\nLX"));
+    assert_eq!(Some("no `S` found in pipe map"), trace("F7\nLJ"));
+    assert_eq!(Some("more than one `S` found in pipe map"), trace("SS\nLJ"));
+    assert_eq!(
+        Some("dead end: `S` connects to fewer than two pipes"),
+        trace("S-\n..")
+    );
+    assert_eq!(
+        Some("ambiguous start: `S` connects to more than two pipes"),
+        trace(".|.\n-S-\n.|.")
+    );
+    assert_eq!(Some("dead end: the loop is broken"), trace("S-7\n|.|\nL-."));
+}
+
+#[test]
+fn classify_tiles() {
+    for filename in ["test2.txt", "test4.txt", PART2_FILE] {
+        let map = read_map(filename).unwrap();
+        let pipe_loop = map.trace().unwrap();
+        let inside = map
+            .classify(&pipe_loop)
+            .iter()
+            .flatten()
+            .filter(|tile| **tile == Tile::Inside)
+            .count();
+        assert_eq!(pipe_loop.count_points_in_loop(), inside, "{filename}");
+    }
+}
+
+#[test]
+fn render_loop() {
+    let map = PipeMap::new("7F-7.\n.|.|.\n-L-SF").unwrap();
+    let pipe_loop = map.trace().unwrap();
+
+    assert_eq!(
+        "O┌─┐O\nO│I│O\nO└─┘O\n",
+        map.render(&pipe_loop, Style::Plain)
+    );
+    assert!(map
+        .render(&pipe_loop, Style::Color)
+        .starts_with("\x1b[2m┐\x1b[0m\x1b[1m┌\x1b[0m"));
+}
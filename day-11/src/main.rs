@@ -6,7 +6,10 @@ const EMPTY_SPACE: u8 = b'.';
 const GALAXY: u8 = b'#';
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-11 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-11 p<n> \
+                 | <query> <file> <row factor> <col factor> ...\n\
+                 queries: expand | nearest <metric> <galaxy> <k> | farthest <metric> \
+                 | matrix <metric> [<output>]";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -17,6 +20,71 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE, 1000000));
             }
+            "expand" | "nearest" | "farthest" | "matrix" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                let (galaxies, args) = match args.as_slice() {
+                    [file, row_factor, col_factor, args @ ..] => {
+                        (read_galaxies(file, row_factor, col_factor), args)
+                    }
+                    _ => {
+                        eprintln!("{usage}");
+                        return;
+                    }
+                };
+                let galaxies = match galaxies {
+                    Ok(galaxies) => galaxies,
+                    Err(e) => {
+                        eprintln!("An error occurred:\n{e}");
+                        return;
+                    }
+                };
+
+                let result = match (part.as_str(), args) {
+                    ("expand", []) => {
+                        println!("Sum is {}", sum_galaxy_distances(&galaxies));
+                        Ok(())
+                    }
+                    ("nearest", [metric, index, k]) => {
+                        match (Metric::new(metric), index.parse(), k.parse()) {
+                            (Ok(metric), Ok(index), Ok(k)) => nearest(&galaxies, index, k, metric)
+                                .map(|nearest| {
+                                    for (other, dist) in nearest {
+                                        println!("{other}: {dist}");
+                                    }
+                                }),
+                            (Err(e), _, _) => Err(e),
+                            _ => Err("couldn't parse galaxy or k"),
+                        }
+                    }
+                    ("farthest", [metric]) => {
+                        Metric::new(metric).map(|metric| match farthest_pair(&galaxies, metric) {
+                            Some((g1, g2, dist)) => println!("{g1} and {g2}: {dist}"),
+                            None => println!("No pairs of galaxies"),
+                        })
+                    }
+                    ("matrix", [metric, output @ ..]) if output.len() <= 1 => Metric::new(metric)
+                        .and_then(|metric| {
+                            let matrix = write_matrix(&distance_matrix(&galaxies, metric));
+                            match output {
+                                [output] => match std::fs::write(output, matrix) {
+                                    Ok(()) => Ok(()),
+                                    Err(_) => Err("could not write file"),
+                                },
+                                _ => {
+                                    print!("{matrix}");
+                                    Ok(())
+                                }
+                            }
+                        }),
+                    _ => {
+                        eprintln!("{usage}");
+                        return;
+                    }
+                };
+                if let Err(e) = result {
+                    eprintln!("An error occurred:\n{e}");
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -24,27 +92,47 @@ fn main() {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct Galaxy {
     row: i64,
     col: i64,
 }
 
 impl Galaxy {
-    fn dist(&self, other: &Self) -> u64 {
-        self.row.abs_diff(other.row) + self.col.abs_diff(other.col)
+    fn dist(&self, other: &Self, metric: Metric) -> f64 {
+        let (rows, cols) = (self.row.abs_diff(other.row), self.col.abs_diff(other.col));
+        match metric {
+            Metric::Manhattan => (rows + cols) as f64,
+            Metric::Chebyshev => rows.max(cols) as f64,
+            Metric::Euclidean => (rows as f64).hypot(cols as f64),
+        }
     }
 }
 
-fn parse_galaxies(image: &Vec<Vec<u8>>, distance_factor: i64) -> Vec<Galaxy> {
-    let distance_additive = if distance_factor == 1 {
-        1
-    } else {
-        distance_factor - 1
-    };
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean,
+}
+
+impl Metric {
+    fn new(name: &str) -> Result<Self, &'static str> {
+        match name {
+            "manhattan" => Ok(Self::Manhattan),
+            "chebyshev" => Ok(Self::Chebyshev),
+            "euclidean" => Ok(Self::Euclidean),
+            _ => Err("unknown distance metric"),
+        }
+    }
+}
+
+// every empty row becomes `row_factor` rows, and every empty column `col_factor` columns
+fn parse_galaxies(image: &[Vec<u8>], row_factor: i64, col_factor: i64) -> Vec<Galaxy> {
     let rows: Vec<i64> = (0..image.len())
         .scan(0, |offset, row| {
             if image[row].iter().all(|b| *b == EMPTY_SPACE) {
-                *offset += distance_additive;
+                *offset += row_factor - 1;
             }
             Some(*offset + row as i64)
         })
@@ -53,7 +141,7 @@ fn parse_galaxies(image: &Vec<Vec<u8>>, distance_factor: i64) -> Vec<Galaxy> {
     let cols: Vec<i64> = (0..image[0].len())
         .scan(0, |offset, col| {
             if image.iter().map(|row| row[col]).all(|b| b == EMPTY_SPACE) {
-                *offset += distance_additive;
+                *offset += col_factor - 1;
             }
             Some(*offset + col as i64)
         })
@@ -80,40 +168,146 @@ fn parse_galaxies(image: &Vec<Vec<u8>>, distance_factor: i64) -> Vec<Galaxy> {
         .collect()
 }
 
+// once sorted, each coordinate is at least as far along as all those before it, so its
+// distances to them add up to `coord * index - prefix_sum`
+fn sum_axis_distances(mut coords: Vec<i64>) -> u64 {
+    coords.sort_unstable();
+
+    let mut prefix_sum: i128 = 0;
+    let mut sum: i128 = 0;
+    for (index, &coord) in coords.iter().enumerate() {
+        sum += coord as i128 * index as i128 - prefix_sum;
+        prefix_sum += coord as i128;
+    }
+    sum as u64
+}
+
+// manhattan distances split into independent row and column parts
 fn sum_galaxy_distances(galaxies: &[Galaxy]) -> u64 {
-    let mut sum = 0;
+    sum_axis_distances(galaxies.iter().map(|g| g.row).collect())
+        + sum_axis_distances(galaxies.iter().map(|g| g.col).collect())
+}
+
+// the `k` galaxies closest to the one at `index`, closest first, as (index, distance)
+fn nearest(
+    galaxies: &[Galaxy],
+    index: usize,
+    k: usize,
+    metric: Metric,
+) -> Result<Vec<(usize, f64)>, &'static str> {
+    let galaxy = match galaxies.get(index) {
+        Some(galaxy) => galaxy,
+        None => return Err("no galaxy with that index"),
+    };
+
+    let mut others: Vec<(usize, f64)> = galaxies
+        .iter()
+        .enumerate()
+        .filter(|(other, _)| *other != index)
+        .map(|(other, g)| (other, galaxy.dist(g, metric)))
+        .collect();
+    others.sort_by(|(i1, d1), (i2, d2)| d1.total_cmp(d2).then(i1.cmp(i2)));
+    others.truncate(k);
+
+    Ok(others)
+}
+
+// the first pair found at the largest distance, as (index, index, distance)
+fn farthest_pair(galaxies: &[Galaxy], metric: Metric) -> Option<(usize, usize, f64)> {
+    let mut farthest: Option<(usize, usize, f64)> = None;
+
     for (i1, g1) in galaxies.iter().enumerate() {
-        for g2 in &galaxies[i1..] {
-            sum += g1.dist(g2);
+        for (i2, g2) in galaxies.iter().enumerate().skip(i1 + 1) {
+            let dist = g1.dist(g2, metric);
+            if farthest.is_none_or(|(_, _, max)| dist > max) {
+                farthest = Some((i1, i2, dist));
+            }
         }
     }
-    sum
+
+    farthest
 }
 
-fn part1(filename: &str) -> u64 {
-    let file = std::fs::read_to_string(filename).unwrap();
+fn distance_matrix(galaxies: &[Galaxy], metric: Metric) -> Vec<Vec<f64>> {
+    galaxies
+        .iter()
+        .map(|g1| galaxies.iter().map(|g2| g1.dist(g2, metric)).collect())
+        .collect()
+}
+
+// as csv, with a header row and column of galaxy indices
+fn write_matrix(matrix: &[Vec<f64>]) -> String {
+    let mut csv = String::new();
+
+    let header = (0..matrix.len()).map(|index| index.to_string());
+    csv.push_str(
+        &std::iter::once(String::new())
+            .chain(header)
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+    csv.push('\n');
+    for (index, row) in matrix.iter().enumerate() {
+        let dists = row.iter().map(|dist| dist.to_string());
+        csv.push_str(
+            &std::iter::once(index.to_string())
+                .chain(dists)
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        csv.push('\n');
+    }
+
+    csv
+}
+
+fn read_galaxies(
+    filename: &str,
+    row_factor: &str,
+    col_factor: &str,
+) -> Result<Vec<Galaxy>, &'static str> {
+    match (row_factor.parse(), col_factor.parse()) {
+        (Ok(row_factor), Ok(col_factor)) if row_factor < 1 || col_factor < 1 => {
+            Err("expansion factor has to be at least 1")
+        }
+        (Ok(row_factor), Ok(col_factor)) => Ok(parse_galaxies(
+            &read_image(filename)?,
+            row_factor,
+            col_factor,
+        )),
+        _ => Err("couldn't parse expansion factor"),
+    }
+}
+
+fn read_image(filename: &str) -> Result<Vec<Vec<u8>>, &'static str> {
+    let file = match std::fs::read_to_string(filename) {
+        Ok(file) => file,
+        Err(_) => return Err("could not read file"),
+    };
 
-    // read image
     let image: Vec<Vec<u8>> = file
         .split_ascii_whitespace()
         .map(|row| row.as_bytes().to_vec())
         .collect();
+    match image.first() {
+        Some(first) if image.iter().all(|row| row.len() == first.len()) => Ok(image),
+        Some(_) => Err("image is not rectangular"),
+        None => Err("empty image"),
+    }
+}
+
+fn part1(filename: &str) -> u64 {
+    let image = read_image(filename).expect("Should be able to read the image");
 
-    let galaxies = parse_galaxies(&image, 1);
+    let galaxies = parse_galaxies(&image, 2, 2);
 
     sum_galaxy_distances(&galaxies)
 }
 
 fn part2(filename: &str, distance_factor: i64) -> u64 {
-    let file = std::fs::read_to_string(filename).unwrap();
-
-    // read image
-    let image: Vec<Vec<u8>> = file
-        .split_ascii_whitespace()
-        .map(|row| row.as_bytes().to_vec())
-        .collect();
+    let image = read_image(filename).expect("Should be able to read the image");
 
-    let galaxies = parse_galaxies(&image, distance_factor);
+    let galaxies = parse_galaxies(&image, distance_factor, distance_factor);
 
     sum_galaxy_distances(&galaxies)
 }
@@ -142,3 +336,72 @@ fn part2_example1() {
 fn part2_puzzle() {
     assert_eq!(597714117556, part2(PART2_FILE, 1000000));
 }
+
+#[test]
+fn separate_expansion_factors() {
+    let image = read_image("test1.txt").unwrap();
+
+    // rows 3 and 7 and columns 2, 5 and 8 are empty
+    let galaxies = parse_galaxies(&image, 10, 1);
+    assert_eq!(Galaxy { row: 0, col: 3 }, galaxies[0]);
+    assert_eq!(Galaxy { row: 27, col: 4 }, galaxies[8]);
+
+    let brute_force = |galaxies: &[Galaxy]| {
+        let mut sum = 0;
+        for (index, g1) in galaxies.iter().enumerate() {
+            for g2 in &galaxies[index + 1..] {
+                sum += g1.row.abs_diff(g2.row) + g1.col.abs_diff(g2.col);
+            }
+        }
+        sum
+    };
+    assert_eq!(brute_force(&galaxies), sum_galaxy_distances(&galaxies));
+    assert_eq!(Ok(galaxies), read_galaxies("test1.txt", "10", "1"));
+    assert_eq!(
+        Err("expansion factor has to be at least 1"),
+        read_galaxies("test1.txt", "2", "-3")
+    );
+    assert_eq!(
+        brute_force(&parse_galaxies(&image, 3, 7)),
+        sum_galaxy_distances(&parse_galaxies(&image, 3, 7))
+    );
+}
+
+#[test]
+fn distance_metrics() {
+    let (g1, g2) = (Galaxy { row: 0, col: 0 }, Galaxy { row: -3, col: 4 });
+    assert_eq!(7.0, g1.dist(&g2, Metric::Manhattan));
+    assert_eq!(4.0, g1.dist(&g2, Metric::Chebyshev));
+    assert_eq!(5.0, g1.dist(&g2, Metric::Euclidean));
+    assert_eq!(Err("unknown distance metric"), Metric::new("taxicab"));
+}
+
+#[test]
+fn galaxy_queries() {
+    let image = read_image("test1.txt").unwrap();
+    let galaxies = parse_galaxies(&image, 2, 2);
+
+    // galaxy 5 is 9 steps from galaxy 9 in the puzzle's 1-based numbering
+    let matrix = distance_matrix(&galaxies, Metric::Manhattan);
+    assert_eq!(9.0, matrix[4][8]);
+    assert_eq!(374.0, matrix.iter().flatten().sum::<f64>() / 2.0);
+
+    let csv = write_matrix(&matrix);
+    assert_eq!(10, csv.lines().count());
+    assert!(csv.starts_with(",0,1,2,3,4,5,6,7,8\n0,0,6,6,9,"));
+
+    assert_eq!(
+        Ok(vec![(1, 6.0), (2, 6.0)]),
+        nearest(&galaxies, 0, 2, Metric::Manhattan)
+    );
+    assert_eq!(
+        Err("no galaxy with that index"),
+        nearest(&galaxies, 9, 1, Metric::Manhattan)
+    );
+
+    assert_eq!(
+        Some((1, 7, 19.0)),
+        farthest_pair(&galaxies, Metric::Manhattan)
+    );
+    assert_eq!(None, farthest_pair(&galaxies[..1], Metric::Euclidean));
+}
@@ -1,15 +1,17 @@
+use std::collections::HashMap;
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
 
 const PART1_FILE: &str = "part1.txt";
 const PART2_FILE: &str = "part2.txt";
 const WORKING: char = '.';
 const BROKEN: char = '#';
 const UNKNOWN: char = '?';
+const GROUP_DELIM: char = ',';
+const UNFOLD_COPIES: usize = 5;
 
 fn main() {
-    let usage = "Incorrect arguements!\nUsage: day-12 p<n>";
+    let usage = "Incorrect arguements!\nUsage: day-12 p<n> | arrangements <file> <copies>";
     if let Some(part) = env::args().nth(1) {
         match part.as_str() {
             "p1" => {
@@ -20,6 +22,30 @@ fn main() {
                 println!("Reading `{PART2_FILE}`");
                 println!("Sum is {}", part2(PART2_FILE));
             }
+            "arrangements" => {
+                let args = env::args().skip(2).collect::<Vec<_>>();
+                match args.as_slice() {
+                    [file, copies] => {
+                        let counts = match copies.parse() {
+                            Ok(copies) => read_records(file).map(|records| {
+                                records
+                                    .iter()
+                                    .map(|record| record.unfold(copies).arrangements())
+                                    .collect::<Vec<_>>()
+                            }),
+                            Err(_) => Err("couldn't parse copies"),
+                        };
+                        match counts {
+                            Ok(counts) => {
+                                counts.iter().for_each(|count| println!("{count}"));
+                                println!("Sum is {}", counts.iter().sum::<u64>());
+                            }
+                            Err(e) => eprintln!("An error occurred:\n{e}"),
+                        }
+                    }
+                    _ => eprintln!("{usage}"),
+                }
+            }
             _ => eprintln!("{usage}"),
         }
     } else {
@@ -27,155 +53,117 @@ fn main() {
     }
 }
 
-fn process_line(springs: Vec<&str>, nums: Vec<usize>) -> usize {
-    let mut section_permutations: Vec<usize> = vec![];
-    let mut spring_index = 0;
-    let mut nums_index = 0;
-
-    while let Some(spring) = springs.get(spring_index) {
-        let unknown_count = spring.chars().filter(|c| *c == UNKNOWN).count();
-        let broken_count = spring.chars().filter(|c| *c == BROKEN).count();
-
-        if broken_count == nums[nums_index] {
-            println!(
-                "complete broken fit {spring:?} with {:?} at {:?}",
-                nums[nums_index], nums_index
-            );
-            section_permutations.push(1);
-        } else if unknown_count == nums[nums_index] {
-            println!(
-                "complete unknown fit {spring:?} with {:?} at {:?}",
-                nums[nums_index], nums_index
-            );
-            section_permutations.push(1);
-        } else if unknown_count == nums[nums_index] + 1 {
-            println!(
-                "+1 fit {spring:?} with {:?} at {:?}",
-                nums[nums_index], nums_index
-            );
-            section_permutations.push(nums[nums_index] + 1);
-        } else if unknown_count + broken_count == nums[nums_index] {
-            println!(
-                "broken + unknown fit {spring:?} with {:?} at {:?}",
-                nums[nums_index], nums_index
-            );
-            section_permutations.push(1);
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Record {
+    springs: Vec<char>,
+    // sizes of the contiguous groups of broken springs, in order
+    groups: Vec<usize>,
+}
+
+impl Record {
+    fn new(line: &str) -> Result<Self, &'static str> {
+        let (springs, groups) = match line.split_once(' ') {
+            Some(split) => split,
+            None => return Err("no ` ` found in Record"),
+        };
+
+        let springs = springs.chars().collect::<Vec<_>>();
+        if springs
+            .iter()
+            .any(|c| ![WORKING, BROKEN, UNKNOWN].contains(c))
+        {
+            return Err("unknown spring condition in Record");
         }
 
-        spring_index += 1;
-        nums_index += 1;
-        continue;
-
-        let new_limit = nums_index + 2;
-        if let Some(num_slice) = nums.get(nums_index..new_limit) {
-            if unknown_count == num_slice.iter().sum::<usize>() + num_slice.len() - 1 {
-                println!(
-                    "multi-fit {spring:?} with {:?} at {:?}",
-                    num_slice,
-                    nums_index..new_limit
-                );
-                section_permutations.push(nums[nums_index]);
-
-                spring_index += 1;
-                nums_index += 2;
-                continue;
+        let groups = groups
+            .split(GROUP_DELIM)
+            .map(|n| match n.parse() {
+                Ok(0) => Err("zero group size in Record"),
+                Ok(n) => Ok(n),
+                Err(_) => Err("couldn't parse group size in Record"),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { springs, groups })
+    }
+
+    // the springs repeated `copies` times joined by unknowns, and the groups repeated alongside
+    fn unfold(&self, copies: usize) -> Self {
+        let mut springs = vec![];
+        for copy in 0..copies {
+            if copy > 0 {
+                springs.push(UNKNOWN);
             }
+            springs.extend(&self.springs);
+        }
+
+        Self {
+            springs,
+            groups: self.groups.repeat(copies),
         }
+    }
 
-        spring_index += 1;
-        nums_index += 1;
+    fn arrangements(&self) -> u64 {
+        self.count(0, 0, 0, &mut HashMap::new())
+    }
 
-        if nums_index >= nums.len() {
-            break;
+    // arrangements of the springs from `pos` on, given that `group` groups are complete and
+    // the springs just before `pos` end in a run of `run` broken ones
+    fn count(
+        &self,
+        pos: usize,
+        group: usize,
+        run: usize,
+        memo: &mut HashMap<(usize, usize, usize), u64>,
+    ) -> u64 {
+        let Some(&spring) = self.springs.get(pos) else {
+            let done = group == self.groups.len() && run == 0;
+            let closing = group + 1 == self.groups.len() && run == self.groups[group];
+            return (done || closing) as u64;
+        };
+        if let Some(&count) = memo.get(&(pos, group, run)) {
+            return count;
+        }
+
+        let mut count = 0;
+        if spring != WORKING && group < self.groups.len() && run < self.groups[group] {
+            count += self.count(pos + 1, group, run + 1, memo);
+        }
+        if spring != BROKEN {
+            if run == 0 {
+                count += self.count(pos + 1, group, 0, memo);
+            } else if run == self.groups[group] {
+                count += self.count(pos + 1, group + 1, 0, memo);
+            }
         }
+
+        memo.insert((pos, group, run), count);
+        count
     }
+}
 
-    println!("Done at s:{:?}, n:{:?} left", spring_index, nums_index);
-
-    // let broken_counts: Vec<usize> = springs
-    //     .iter()
-    //     .map(|s| s.chars().filter(|c| *c == BROKEN).count())
-    //     .collect();
-
-    // if broken_counts.len() != springs.len() {
-    //     return (springs.to_vec(), nums.to_vec());
-    // }
-
-    // let mut n_offset = 0;
-    // let mut remove_indices = vec![];
-    // for count in broken_counts {
-    //     if let Some(index) = nums.iter().skip(n_offset).position(|n| *n == count) {
-    //         n_offset = index + n_offset;
-    //         remove_indices.push(n_offset);
-    //         n_offset += 1;
-    //     }
-    // }
-
-    // println!("{remove_indices:?}");
-
-    // for (i, index) in remove_indices.iter().enumerate() {
-    //     springs.remove(*index - i);
-    //     nums.remove(*index - i);
-    // }
-
-    // let (bc_offset, n_offset) = broken_counts
-    //     .iter()
-    //     .enumerate()
-    //     .find_map(|(i, bc)| {
-    //         if let Some(pos) = nums.iter().position(|n| n == bc) {
-    //             Some((i, pos))
-    //         } else {
-    //             None
-    //         }
-    //     })
-    //     .unwrap();
-
-    // println!("{bc_offset:?} {n_offset:?}");
-    // for (index, count) in broken_counts[bc_offset..].iter().enumerate().rev() {
-    //     if *count == 0 {
-    //         continue;
-    //     }
-
-    //     if index >= nums.len() {
-    //         break;
-    //     }
-
-    //     println!("{index:?} {:?}", broken_counts[index]);
-
-    //     if broken_counts[index] == nums[n_offset..][index] {
-    //         springs.remove(index + n_offset);
-    //         nums.remove(index + n_offset);
-    //     }
-    // }
-
-    // (springs, nums)
-    let p = section_permutations.iter().product();
-    println!("result: {p:?}");
-    p
+fn read_records(filename: &str) -> Result<Vec<Record>, &'static str> {
+    let input = match fs::read_to_string(filename) {
+        Ok(input) => input,
+        Err(_) => return Err("could not read file"),
+    };
+
+    input.lines().map(Record::new).collect()
 }
 
-fn part1(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
-
-    file.lines().fold(0, |sum, line| {
-        let line = line.unwrap();
-        println!("\n{line}");
-        let (springs, nums) = line.split_once(' ').unwrap();
-        let springs: Vec<&str> = springs.split(WORKING).filter(|s| !s.is_empty()).collect();
-        println!("{springs:?}");
-        let nums: Vec<usize> = nums.split(',').map(|n| n.parse().unwrap()).collect();
-        println!("{nums:?}");
-
-        sum + process_line(springs, nums)
-    })
+fn part1(filename: &str) -> u64 {
+    let records = read_records(filename).expect("Should be able to parse the records");
+
+    records.iter().map(|record| record.arrangements()).sum()
 }
 
-fn part2(filename: &str) -> usize {
-    let file = File::open(filename).expect("Should be able to read the file");
-    let file = BufReader::new(file);
+fn part2(filename: &str) -> u64 {
+    let records = read_records(filename).expect("Should be able to parse the records");
 
-    0
+    records
+        .iter()
+        .map(|record| record.unfold(UNFOLD_COPIES).arrangements())
+        .sum()
 }
 
 #[test]
@@ -183,17 +171,42 @@ fn part1_example() {
     assert_eq!(21, part1("test1.txt"));
 }
 
-// #[test]
-// fn part1_puzzle() {
-//     assert_eq!(250058342, part1(PART1_FILE));
-// }
+#[test]
+fn part1_puzzle() {
+    assert_eq!(7732, part1(PART1_FILE));
+}
 
-// #[test]
-// fn part2_example() {
-//     assert_eq!(5905, part2("test2.txt"));
-// }
+#[test]
+fn part2_example() {
+    assert_eq!(525152, part2("test2.txt"));
+}
+
+#[test]
+fn part2_puzzle() {
+    assert_eq!(4500070301581, part2(PART2_FILE));
+}
 
-// #[test]
-// fn part2_puzzle() {
-//     assert_eq!(250506580, part2(PART2_FILE));
-// }
+#[test]
+fn record_arrangements() {
+    let record = Record::new(".# 1").unwrap();
+    assert_eq!(
+        Record {
+            springs: ".#?.#".chars().collect(),
+            groups: vec![1, 1],
+        },
+        record.unfold(2)
+    );
+
+    let record = Record::new("?###???????? 3,2,1").unwrap();
+    assert_eq!(10, record.arrangements());
+    assert_eq!(506250, record.unfold(UNFOLD_COPIES).arrangements());
+    assert_eq!(0, Record::new("##.# 1,1").unwrap().arrangements());
+    assert_eq!(1, Record::new("??? 3").unwrap().arrangements());
+
+    assert_eq!(Err("no ` ` found in Record"), Record::new("???"));
+    assert_eq!(
+        Err("unknown spring condition in Record"),
+        Record::new("?x? 1")
+    );
+    assert_eq!(Err("zero group size in Record"), Record::new("??? 1,0"));
+}